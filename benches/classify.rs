@@ -0,0 +1,101 @@
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use tokau::bulk::{classify_into, decode_slice_into};
+use tokau::{Name, Position, Space, Token, TokenIter, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum Control {
+    Bos,
+    Eos,
+    AudioStart,
+    AudioEnd,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(4096)]
+struct AudioTokens(u32);
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(1024)]
+struct ImageTokens(u32);
+
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum Multimodal {
+    Control(Control),
+    Audio(AudioTokens),
+    Image(ImageTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+// Deterministic mix of ids from every segment
+fn ids(len: usize) -> Vec<u32> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 60_000) as u32
+        })
+        .collect()
+}
+
+fn bench_classify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("classify");
+    for len in [1 << 12, 1 << 20] {
+        let ids = ids(len);
+        group.throughput(Throughput::Elements(len as u64));
+
+        group.bench_with_input(BenchmarkId::new("classify_into", len), &ids, |b, ids| {
+            let mut out = vec![0u8; ids.len()];
+            b.iter(|| classify_into::<Multimodal, u8>(black_box(ids), &mut out));
+        });
+        group.bench_with_input(BenchmarkId::new("kinds", len), &ids, |b, ids| {
+            b.iter(|| {
+                black_box(ids)
+                    .iter()
+                    .copied()
+                    .kinds::<Multimodal>()
+                    .collect::<Vec<_>>()
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for len in [1 << 12, 1 << 20] {
+        let ids = ids(len);
+        group.throughput(Throughput::Elements(len as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("decode_slice_into", len),
+            &ids,
+            |b, ids| {
+                let mut out = Vec::with_capacity(ids.len());
+                b.iter(|| {
+                    out.clear();
+                    decode_slice_into::<Multimodal>(black_box(ids), &mut out).unwrap();
+                });
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("decode", len), &ids, |b, ids| {
+            b.iter(|| {
+                black_box(ids)
+                    .iter()
+                    .copied()
+                    .decode::<Multimodal>()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap()
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_classify, bench_decode);
+criterion_main!(benches);
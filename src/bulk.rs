@@ -0,0 +1,320 @@
+use std::marker::PhantomData;
+
+use crate::error::DecodeError;
+use crate::segment::Segmented;
+
+/// Integer type for segment indices ("token type ids"), see [`SegmentTable`]
+pub trait SegmentIndex: Copy {
+    /// Largest index the type holds
+    const MAX: u32;
+
+    /// Convert an index known to be at most `MAX`
+    fn from_index(index: u32) -> Self;
+}
+
+macro_rules! impl_segment_index {
+    ($($ty:ty),*) => {
+        $(
+            impl SegmentIndex for $ty {
+                const MAX: u32 = <$ty>::MAX as u32;
+
+                fn from_index(index: u32) -> Self {
+                    index as $ty
+                }
+            }
+        )*
+    };
+}
+
+impl_segment_index!(u8, u16, u32);
+
+// Ids per block. Fixed-size blocks let the compiler vectorize the compare
+// and add across ids, one segment end at a time.
+const BLOCK: usize = 64;
+
+/// Segment boundaries of a space, for classifying large id slices.
+///
+/// The index of an id is its position in `S::SEGMENTS`, or `SEGMENTS.len()`
+/// for ids at or above RESERVED (the dynamic tail, or out of range in a
+/// closed space). It is computed by counting the segment ends at or below the
+/// id, which has no branches and vectorizes.
+///
+/// Counting ends only finds the right segment when segments are contiguous:
+/// each starts where the previous one ends, from 0 up to RESERVED. Derived
+/// spaces always are; hand-written `Segmented` impls are checked by
+/// [`new`](Self::new).
+pub struct SegmentTable<S> {
+    ends: Vec<u32>,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: Segmented> SegmentTable<S> {
+    /// Table of the space's segment ends
+    ///
+    /// # Panics
+    ///
+    /// If the segments leave a gap, overlap or do not end at RESERVED.
+    pub fn new() -> Self {
+        let mut end = 0;
+        for segment in S::SEGMENTS {
+            assert_eq!(
+                segment.offset, end,
+                "segment {} does not start where the previous one ends",
+                segment.name
+            );
+            end = segment.end();
+        }
+        assert_eq!(end, S::RESERVED, "segments do not end at RESERVED");
+
+        SegmentTable {
+            ends: S::SEGMENTS.iter().map(|segment| segment.end()).collect(),
+            _space: PhantomData,
+        }
+    }
+
+    /// Segment index of one id
+    pub fn index_of(&self, id: u32) -> u32 {
+        self.ends.iter().map(|end| u32::from(id >= *end)).sum()
+    }
+
+    /// Kind of the segment at `index`, the dynamic kind for `SEGMENTS.len()`
+    pub fn kind_at(&self, index: u32) -> Option<S::Kind> {
+        match S::SEGMENTS.get(index as usize) {
+            Some(segment) => Some(segment.kind),
+            None if index as usize == S::SEGMENTS.len() => S::DYNAMIC,
+            None => None,
+        }
+    }
+
+    /// Number of ids in each segment, indexed like [`kind_at`](Self::kind_at)
+    /// with the dynamic tail last
+    pub fn counts(&self, ids: &[u32]) -> Vec<u64> {
+        let mut counts = vec![0u64; self.ends.len() + 1];
+        let mut indices = [0u32; BLOCK];
+        for ids in ids.chunks(BLOCK) {
+            let indices = &mut indices[..ids.len()];
+            self.classify_into(ids, indices);
+            for index in indices.iter() {
+                counts[*index as usize] += 1;
+            }
+        }
+        counts
+    }
+
+    /// Write the segment index of each id in `ids` to `out`.
+    ///
+    /// # Panics
+    ///
+    /// If the slices differ in length, or `I` cannot hold `SEGMENTS.len()`.
+    pub fn classify_into<I: SegmentIndex>(&self, ids: &[u32], out: &mut [I]) {
+        assert_eq!(ids.len(), out.len(), "output length must match ids");
+        assert!(
+            S::SEGMENTS.len() as u64 <= u64::from(I::MAX),
+            "segment index type too small for this space"
+        );
+
+        let mut ids_blocks = ids.chunks_exact(BLOCK);
+        let mut out_blocks = out.chunks_exact_mut(BLOCK);
+        for (ids, out) in ids_blocks.by_ref().zip(out_blocks.by_ref()) {
+            let ids: &[u32; BLOCK] = ids.try_into().unwrap();
+            let mut counts = [0u32; BLOCK];
+            for &end in &self.ends {
+                for lane in 0..BLOCK {
+                    counts[lane] += u32::from(ids[lane] >= end);
+                }
+            }
+            for (out, count) in out.iter_mut().zip(counts) {
+                *out = I::from_index(count);
+            }
+        }
+        for (out, &id) in out_blocks
+            .into_remainder()
+            .iter_mut()
+            .zip(ids_blocks.remainder())
+        {
+            *out = I::from_index(self.index_of(id));
+        }
+    }
+}
+
+impl<S: Segmented> Default for SegmentTable<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Clone for SegmentTable<S> {
+    fn clone(&self) -> Self {
+        SegmentTable {
+            ends: self.ends.clone(),
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for SegmentTable<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SegmentTable")
+            .field("ends", &self.ends)
+            .finish()
+    }
+}
+
+/// Write the segment index of each id to `out`, see [`SegmentTable`].
+///
+/// # Panics
+///
+/// If the slices differ in length, `I` cannot hold `SEGMENTS.len()`, or the
+/// segments are not contiguous.
+pub fn classify_into<S: Segmented, I: SegmentIndex>(ids: &[u32], out: &mut [I]) {
+    SegmentTable::<S>::new().classify_into(ids, out);
+}
+
+/// Decode a slice of ids, appending the tokens to `out`. On failure `out` is
+/// left as it was and the error carries the index of the failing id.
+pub fn decode_slice_into<S: Segmented>(ids: &[u32], out: &mut Vec<S>) -> Result<(), DecodeError> {
+    let len = out.len();
+    out.reserve(ids.len());
+    for (index, &id) in ids.iter().enumerate() {
+        match S::decode_at(index, id) {
+            Ok(token) => out.push(token),
+            Err(error) => {
+                out.truncate(len);
+                return Err(error);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TokauError;
+    use crate::segment::Segment;
+    use crate::space::tests::{DynamicGingerSpace, DynamicGingerSpaceKind, GingerSpace};
+    use crate::space::{Position, TokenSpace};
+    use crate::token::Token;
+    use crate::token::tests::*;
+
+    // Mao tokens one id after the Ginger tokens end, leaving id 5 unclaimed
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    enum GappedSpace {
+        Ginger(GingerToken),
+        Mao(MaoToken),
+    }
+
+    impl Position<GingerToken> for GappedSpace {
+        const OFFSET: u32 = 0;
+    }
+
+    impl Position<MaoToken> for GappedSpace {
+        const OFFSET: u32 = GingerToken::COUNT + 1;
+    }
+
+    impl TokenSpace for GappedSpace {
+        const RESERVED: u32 = GingerToken::COUNT + 1 + MaoToken::COUNT;
+
+        fn value(self) -> u32 {
+            match self {
+                GappedSpace::Ginger(token) => Self::position_of(token),
+                GappedSpace::Mao(token) => Self::position_of(token),
+            }
+        }
+    }
+
+    impl TryFrom<u32> for GappedSpace {
+        type Error = TokauError;
+
+        fn try_from(id: u32) -> Result<Self, Self::Error> {
+            Self::try_as::<GingerToken>(id)
+                .map(GappedSpace::Ginger)
+                .or_else(|| Self::try_as::<MaoToken>(id).map(GappedSpace::Mao))
+                .ok_or(TokauError::OutOfRange {
+                    value: id,
+                    max: Self::RESERVED,
+                })
+        }
+    }
+
+    impl Segmented for GappedSpace {
+        type Kind = ();
+
+        const SEGMENTS: &'static [Segment<()>] = &[
+            Segment {
+                kind: (),
+                name: "Ginger",
+                offset: 0,
+                count: GingerToken::COUNT,
+                tokens: GingerToken::NAMES,
+                variants: GingerToken::VARIANTS,
+                pairs: GingerToken::PAIRS,
+            },
+            Segment {
+                kind: (),
+                name: "Mao",
+                offset: GingerToken::COUNT + 1,
+                count: MaoToken::COUNT,
+                tokens: MaoToken::NAMES,
+                variants: MaoToken::VARIANTS,
+                pairs: MaoToken::PAIRS,
+            },
+        ];
+
+        const DYNAMIC: Option<()> = None;
+
+        fn kind_of(id: u32) -> Option<()> {
+            Self::try_from(id).ok().map(|_| ())
+        }
+
+        fn kind(&self) {}
+    }
+
+    #[test]
+    fn test_classify() {
+        let ids: Vec<u32> = (0..200).map(|n| n * 7).collect();
+        let mut out = vec![0u8; ids.len()];
+        classify_into::<DynamicGingerSpace, _>(&ids, &mut out);
+
+        let table = SegmentTable::<DynamicGingerSpace>::new();
+        for (&id, &index) in ids.iter().zip(&out) {
+            assert_eq!(
+                table.kind_at(u32::from(index)),
+                DynamicGingerSpace::kind_of(id)
+            );
+        }
+        assert_eq!(table.index_of(9), 2);
+        assert_eq!(table.kind_at(4), Some(DynamicGingerSpaceKind::Dynamic));
+        assert_eq!(table.kind_at(5), None);
+
+        // Ginger 0..5, Mao 5..9, Single 9, Text 10..1010, dynamic
+        assert_eq!(
+            table.counts(&[0, 4, 9, 10, 1009, 1010, 5000]),
+            vec![2, 0, 1, 2, 2]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "segment Mao does not start where the previous one ends")]
+    fn test_gapped_segments() {
+        SegmentTable::<GappedSpace>::new();
+    }
+
+    #[test]
+    fn test_decode_slice_into() {
+        let mut out = vec![GingerSpace::Single(SingleToken::Single)];
+        decode_slice_into(&[0, 5], &mut out).unwrap();
+        assert_eq!(
+            out,
+            vec![
+                GingerSpace::Single(SingleToken::Single),
+                GingerSpace::Ginger(GingerToken::TextStart),
+                GingerSpace::Mao(MaoToken::ProgramStart),
+            ]
+        );
+
+        let error = decode_slice_into(&[1, 1010], &mut out).unwrap_err();
+        assert!(matches!(error, DecodeError::OutOfRange { index: 1, .. }));
+        assert_eq!(out.len(), 3);
+    }
+}
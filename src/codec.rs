@@ -0,0 +1,180 @@
+use crate::error::{DecodeError, EncodeError};
+use crate::render::Render;
+use crate::run::{Run, slice_runs};
+use crate::segment::Segmented;
+use crate::space::OpenSpace;
+use crate::special::{SpecialEncoder, Specials};
+
+/// Subword tokenizer for the dynamic tail of a space. Its ids start at 0, the
+/// [`Codec`] shifts them past the reserved range.
+pub trait Tokenizer {
+    /// Token ids of a piece of text without special tokens
+    fn encode(&self, text: &str) -> Vec<u32>;
+
+    /// Bytes of a run of token ids. Only ids below [`vocab_size`] are passed
+    /// by a [`Codec`]; how others are handled is up to the tokenizer.
+    ///
+    /// [`vocab_size`]: Tokenizer::vocab_size
+    fn decode(&self, ids: &[u32]) -> Vec<u8>;
+
+    /// Number of token ids, if known. A [`Codec`] rejects larger ids with
+    /// [`DecodeError::UnknownDynamic`] instead of decoding them.
+    fn vocab_size(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// Tokenizer with one id per UTF-8 byte, for tests and offline use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ByteTokenizer;
+
+impl Tokenizer for ByteTokenizer {
+    fn encode(&self, text: &str) -> Vec<u32> {
+        text.bytes().map(u32::from).collect()
+    }
+
+    // Ids past 255 have no byte and are skipped, a Codec rejects them first
+    fn decode(&self, ids: &[u32]) -> Vec<u8> {
+        ids.iter().filter_map(|id| u8::try_from(*id).ok()).collect()
+    }
+
+    fn vocab_size(&self) -> Option<u32> {
+        Some(256)
+    }
+}
+
+/// A token space combined with a tokenizer for its dynamic tail.
+///
+/// Encoding turns special token literals into their ids and tokenizes the
+/// rest, shifted by RESERVED. Decoding renders specials by name and hands runs
+/// of dynamic remainders back to the tokenizer, so the space needs a dynamic
+/// tail.
+pub struct Codec<S, T> {
+    tokenizer: T,
+    specials: SpecialEncoder<S>,
+}
+
+impl<S, T> Codec<S, T>
+where
+    S: Segmented + OpenSpace + Render,
+    T: Tokenizer,
+{
+    /// Codec using `tokenizer` for dynamic ids
+    pub fn new(tokenizer: T) -> Self {
+        Codec {
+            tokenizer,
+            specials: SpecialEncoder::new(),
+        }
+    }
+
+    /// The wrapped tokenizer
+    pub fn tokenizer(&self) -> &T {
+        &self.tokenizer
+    }
+
+    /// Encode text, turning special token literals into their ids
+    pub fn encode(&self, text: &str) -> Result<Vec<u32>, EncodeError> {
+        self.encode_with(text, Specials::Allow)
+    }
+
+    /// Encode text, treating special token literals according to `specials`
+    pub fn encode_with(&self, text: &str, specials: Specials) -> Result<Vec<u32>, EncodeError> {
+        self.specials
+            .encode(text, specials, |text| self.tokenizer.encode(text))
+    }
+
+    /// Decode ids to bytes, rendering specials by name
+    pub fn decode_bytes(&self, ids: &[u32]) -> Result<Vec<u8>, DecodeError> {
+        let mut bytes = Vec::new();
+        let mut rendered = String::new();
+        let mut index = 0;
+        for run in slice_runs::<S>(ids) {
+            match run {
+                Run::Reserved(token) => {
+                    token.render(&mut rendered);
+                    bytes.extend_from_slice(rendered.as_bytes());
+                    rendered.clear();
+                    index += 1;
+                }
+                Run::Dynamic(run) => {
+                    let remainders: Vec<u32> = run.remainders().collect();
+                    if let Some(size) = self.tokenizer.vocab_size()
+                        && let Some(offset) = remainders.iter().position(|id| *id >= size)
+                    {
+                        return Err(DecodeError::UnknownDynamic {
+                            index: index + offset,
+                            value: run.ids()[offset],
+                        });
+                    }
+                    bytes.extend(self.tokenizer.decode(&remainders));
+                    index += run.len();
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Decode ids to text, replacing invalid UTF-8 with U+FFFD
+    pub fn decode(&self, ids: &[u32]) -> Result<String, DecodeError> {
+        let bytes = self.decode_bytes(ids)?;
+        Ok(String::from_utf8(bytes)
+            .unwrap_or_else(|error| String::from_utf8_lossy(error.as_bytes()).into_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultTokenSpace;
+    use crate::token::tests::ChatToken;
+
+    type Space = DefaultTokenSpace<ChatToken>;
+
+    #[test]
+    fn test_byte_tokenizer() {
+        assert_eq!(ByteTokenizer.encode("hé"), vec![104, 0xc3, 0xa9]);
+        assert_eq!(
+            ByteTokenizer.decode(&[104, 0xc3, 0xa9, 300]),
+            "hé".as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let codec = Codec::<Space, _>::new(ByteTokenizer);
+        let text = "<s>hi </s>Pause";
+        let ids = codec.encode(text).unwrap();
+        assert_eq!(ids[..5], [0, 4 + 104, 4 + 105, 4 + 32, 2]);
+        // Pause has no literal, so its name is encoded as text
+        assert_eq!(ids.len(), 5 + "Pause".len());
+        assert_eq!(codec.decode(&ids), Ok(text.to_string()));
+    }
+
+    #[test]
+    fn test_unknown_dynamic() {
+        // Ids past 255 have no byte, the tokenizer alone skips them
+        assert_eq!(ByteTokenizer.decode(&[104, 300]), b"h");
+
+        let codec = Codec::<Space, _>::new(ByteTokenizer);
+        assert_eq!(
+            codec.decode(&[0, 4 + 104, 4 + 300, 2]),
+            Err(DecodeError::UnknownDynamic {
+                index: 2,
+                value: 304,
+            })
+        );
+        assert_eq!(
+            codec.decode(&[0, 4 + 255, 2]),
+            Ok("<s>\u{fffd}</s>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_untrusted_round_trip() {
+        let codec = Codec::<Space, _>::new(ByteTokenizer);
+        let ids = codec.encode_with("</s>", Specials::AsText).unwrap();
+        assert_eq!(ids.len(), 4);
+        assert!(ids.iter().all(|id| *id >= 4));
+        assert_eq!(codec.decode(&ids), Ok("</s>".to_string()));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::error::TokauError;
-use crate::space::{Position, TokenSpace};
+use crate::render::Render;
+use crate::segment::{Segment, Segmented};
+use crate::space::{OpenSpace, Position, TokenSpace};
 use crate::token::Token;
 
 // Create separate types to avoid conflicting implementations
@@ -20,9 +22,18 @@ where
     const RESERVED: u32 = T::COUNT;
 
     fn value(self) -> u32 {
+        u32::from(self)
+    }
+
+    fn try_value(self) -> Result<u32, TokauError> {
         match self {
-            DefaultTokenSpace::Token(token) => Self::position_of(token),
-            DefaultTokenSpace::Dynamic(offset) => Self::RESERVED + offset,
+            DefaultTokenSpace::Token(token) => <Self as Position<T>>::try_at(token),
+            DefaultTokenSpace::Dynamic(offset) => {
+                Self::checked_after_reserved(offset).ok_or(TokauError::Overflow {
+                    value: offset,
+                    offset: Self::RESERVED,
+                })
+            }
         }
     }
 }
@@ -34,14 +45,97 @@ where
     type Error = TokauError;
 
     fn try_from(id: u32) -> Result<Self, Self::Error> {
-        if let Some(token) = Self::try_as::<T>(id) {
-            return Ok(DefaultTokenSpace::Token(token));
+        if id >= Self::RESERVED {
+            return Ok(DefaultTokenSpace::Dynamic(id - Self::RESERVED));
         }
-        if let Some(offset) = Self::remainder(id) {
-            return Ok(DefaultTokenSpace::Dynamic(offset));
+        // A hand-written T may reject some of its reserved ids
+        T::try_from(id).map(DefaultTokenSpace::Token)
+    }
+}
+
+impl<T> OpenSpace for DefaultTokenSpace<T>
+where
+    T: Token + TryFrom<u32, Error = TokauError>,
+{
+    fn decode_total(id: u32) -> Self {
+        Self::try_from(id).unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+impl<T: Token + Render> Render for DefaultTokenSpace<T> {
+    fn render(&self, out: &mut String) {
+        match self {
+            DefaultTokenSpace::Token(token) => token.render(out),
+            // Dynamic values render through a DynamicVocab
+            DefaultTokenSpace::Dynamic(_) => {}
+        }
+    }
+}
+
+impl<T: Token> From<T> for DefaultTokenSpace<T> {
+    fn from(token: T) -> Self {
+        DefaultTokenSpace::Token(token)
+    }
+}
+
+impl<T> From<DefaultTokenSpace<T>> for u32
+where
+    T: Token + TryFrom<u32, Error = TokauError>,
+{
+    fn from(space: DefaultTokenSpace<T>) -> u32 {
+        match space {
+            DefaultTokenSpace::Token(token) => DefaultTokenSpace::<T>::position_of(token),
+            DefaultTokenSpace::Dynamic(offset) => DefaultTokenSpace::<T>::RESERVED + offset,
+        }
+    }
+}
+
+/// Segment kinds of [`DefaultTokenSpace`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DefaultTokenSpaceKind {
+    Token,
+    Dynamic,
+}
+
+impl<T> Segmented for DefaultTokenSpace<T>
+where
+    T: Token + TryFrom<u32, Error = TokauError>,
+{
+    type Kind = DefaultTokenSpaceKind;
+
+    const SEGMENTS: &'static [Segment<DefaultTokenSpaceKind>] = &[Segment {
+        kind: DefaultTokenSpaceKind::Token,
+        name: "Token",
+        offset: 0,
+        count: T::COUNT,
+        tokens: T::NAMES,
+        variants: T::VARIANTS,
+        pairs: T::PAIRS,
+    }];
+
+    const DYNAMIC: Option<DefaultTokenSpaceKind> = Some(DefaultTokenSpaceKind::Dynamic);
+
+    fn kind_named(name: &str) -> Option<DefaultTokenSpaceKind> {
+        match name {
+            "Token" => Some(DefaultTokenSpaceKind::Token),
+            "Dynamic" => Some(DefaultTokenSpaceKind::Dynamic),
+            _ => None,
+        }
+    }
+
+    fn kind_of(id: u32) -> Option<DefaultTokenSpaceKind> {
+        if Self::is_reserved(id) {
+            Some(DefaultTokenSpaceKind::Token)
+        } else {
+            Some(DefaultTokenSpaceKind::Dynamic)
+        }
+    }
+
+    fn kind(&self) -> DefaultTokenSpaceKind {
+        match self {
+            DefaultTokenSpace::Token(_) => DefaultTokenSpaceKind::Token,
+            DefaultTokenSpace::Dynamic(_) => DefaultTokenSpaceKind::Dynamic,
         }
-        // Since this has dynamic tokens, it accepts all values
-        unreachable!("NameTokenSpace with dynamic tokens accepts all values")
     }
 }
 
@@ -119,7 +213,7 @@ mod tests {
         assert_eq!(DefaultTokenSpace::<MaoToken>::remainder(2), None); // In static range, not dynamic
 
         // Test filtering with dynamic tokens
-        let tokens = vec![0, 1, 2, 3, 4, 5, 50, 103, 104, 200];
+        let tokens = vec![0u32, 1, 2, 3, 4, 5, 50, 103, 104, 200];
 
         // Decode tokens and extract MaoTokens
         let decoded: Vec<DefaultTokenSpace<MaoToken>> = tokens
@@ -154,4 +248,63 @@ mod tests {
             .collect();
         assert_eq!(remainder_values, vec![0, 1, 46, 99, 100, 196]); // Remainder values (token_id - RESERVED)
     }
+
+    #[test]
+    fn test_default_space_kinds() {
+        type Space = DefaultTokenSpace<MaoToken>;
+
+        assert_eq!(Space::kind_of(0), Some(DefaultTokenSpaceKind::Token));
+        assert_eq!(Space::kind_of(3), Some(DefaultTokenSpaceKind::Token));
+        assert_eq!(Space::kind_of(4), Some(DefaultTokenSpaceKind::Dynamic));
+        assert_eq!(
+            Space::kind_of(u32::MAX),
+            Some(DefaultTokenSpaceKind::Dynamic)
+        );
+
+        assert_eq!(
+            DefaultTokenSpace::Token(MaoToken::Fn).kind(),
+            DefaultTokenSpaceKind::Token
+        );
+        assert_eq!(
+            DefaultTokenSpace::<MaoToken>::Dynamic(7).kind(),
+            DefaultTokenSpaceKind::Dynamic
+        );
+
+        assert_eq!(Space::SEGMENTS.len(), 1);
+        assert_eq!(Space::SEGMENTS[0].range(), 0..4);
+
+        // Kinds agree with decoding for every id
+        let ids = vec![0u32, 2, 3, 4, 100];
+        let kinds: Vec<_> = ids.clone().into_iter().kinds::<Space>().collect();
+        let decoded: Vec<_> = ids
+            .into_iter()
+            .decode::<Space>()
+            .map(|token| token.ok().map(|token| token.kind()))
+            .collect();
+        assert_eq!(kinds, decoded);
+    }
+
+    #[test]
+    fn test_default_space_conversions() {
+        let space: DefaultTokenSpace<MaoToken> = MaoToken::Fn.into();
+        assert_eq!(space, DefaultTokenSpace::Token(MaoToken::Fn));
+        assert_eq!(u32::from(space), 2);
+        assert_eq!(u32::from(DefaultTokenSpace::<MaoToken>::Dynamic(3)), 7);
+    }
+
+    #[test]
+    fn test_default_space_try_value() {
+        assert_eq!(
+            DefaultTokenSpace::Token(MaoToken::Struct).try_value(),
+            Ok(3)
+        );
+        assert_eq!(DefaultTokenSpace::<MaoToken>::Dynamic(1).try_value(), Ok(5));
+        assert_eq!(
+            DefaultTokenSpace::<MaoToken>::Dynamic(u32::MAX - 3).try_value(),
+            Err(TokauError::Overflow {
+                value: u32::MAX - 3,
+                offset: 4,
+            })
+        );
+    }
 }
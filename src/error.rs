@@ -1,5 +1,6 @@
 /// Error type for tokau operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum TokauError {
     /// The token ID is out of the valid range for this token space
     OutOfRange {
@@ -8,6 +9,26 @@ pub enum TokauError {
         /// The maximum valid value (exclusive)
         max: u32,
     },
+    /// Offsetting the value into the token space overflows u32
+    Overflow {
+        /// The value being offset
+        value: u32,
+        /// The offset that was added
+        offset: u32,
+    },
+    /// The value cannot be represented as a u32 token ID, or the ID cannot be
+    /// represented in the requested integer type
+    InvalidId {
+        /// The value that failed to convert
+        value: i128,
+    },
+    /// The opening and closing IDs are not a pair declared with `#[pair]`
+    Unpaired {
+        /// The opening ID
+        open: u32,
+        /// The closing ID
+        close: u32,
+    },
 }
 
 impl std::fmt::Display for TokauError {
@@ -16,8 +37,352 @@ impl std::fmt::Display for TokauError {
             TokauError::OutOfRange { value, max } => {
                 write!(f, "Token ID {} is out of valid range [0, {})", value, max)
             }
+            TokauError::Overflow { value, offset } => {
+                write!(
+                    f,
+                    "Token value {} overflows u32 when offset by {}",
+                    value, offset
+                )
+            }
+            TokauError::InvalidId { value } => {
+                write!(f, "Value {} is not a representable token ID", value)
+            }
+            TokauError::Unpaired { open, close } => {
+                write!(
+                    f,
+                    "Token IDs {} and {} are not a declared start/end pair",
+                    open, close
+                )
+            }
         }
     }
 }
 
 impl std::error::Error for TokauError {}
+
+/// Error decoding a sequence of token IDs, locating the bad ID in the stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// The ID is past the reserved range of a space without a dynamic tail
+    OutOfRange {
+        /// Index of the ID in the sequence
+        index: usize,
+        /// The value that was out of range
+        value: u32,
+        /// The maximum valid value (exclusive)
+        max: u32,
+        /// Name of the last segment before the invalid value
+        nearest: Option<&'static str>,
+    },
+    /// The ID is inside the reserved range but no token claims it
+    Gap {
+        /// Index of the ID in the sequence
+        index: usize,
+        /// The value that fell in the gap
+        value: u32,
+        /// Name of the segment whose range covers the value
+        segment: Option<&'static str>,
+    },
+    /// The value is not a valid u32 token ID (e.g. negative)
+    InvalidId {
+        /// Index of the value in the sequence
+        index: usize,
+        /// The value that failed to convert
+        value: i128,
+    },
+    /// Computing an ID from this value would overflow u32
+    Overflow {
+        /// Index of the value in the sequence
+        index: usize,
+        /// The value that overflowed
+        value: u32,
+    },
+    /// A dynamic ID with no entry in the vocabulary used to render it
+    UnknownDynamic {
+        /// Index of the ID in the sequence
+        index: usize,
+        /// The ID that was not found
+        value: u32,
+    },
+    /// The sequence was produced for a different token space layout
+    FingerprintMismatch {
+        /// Fingerprint of the space decoding the sequence
+        expected: u64,
+        /// Fingerprint recorded with the sequence
+        found: u64,
+    },
+}
+
+impl DecodeError {
+    /// Attach the index of the failing ID in a sequence to a [`TokauError`]
+    pub fn at(index: usize, error: TokauError) -> Self {
+        match error {
+            TokauError::OutOfRange { value, max } => DecodeError::OutOfRange {
+                index,
+                value,
+                max,
+                nearest: None,
+            },
+            TokauError::Overflow { value, .. } => DecodeError::Overflow { index, value },
+            TokauError::InvalidId { value } => DecodeError::InvalidId { index, value },
+            // Decoding a single ID never checks pairs
+            TokauError::Unpaired { open, .. } => DecodeError::InvalidId {
+                index,
+                value: open.into(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::OutOfRange {
+                index,
+                value,
+                max,
+                nearest,
+            } => {
+                write!(
+                    f,
+                    "Token ID {} at index {} is out of valid range [0, {})",
+                    value, index, max
+                )?;
+                if let Some(nearest) = nearest {
+                    write!(f, ", past segment {}", nearest)?;
+                }
+                Ok(())
+            }
+            DecodeError::Gap {
+                index,
+                value,
+                segment,
+            } => {
+                write!(
+                    f,
+                    "Token ID {} at index {} is reserved but not assigned to a token",
+                    value, index
+                )?;
+                if let Some(segment) = segment {
+                    write!(f, " in segment {}", segment)?;
+                }
+                Ok(())
+            }
+            DecodeError::InvalidId { index, value } => {
+                write!(
+                    f,
+                    "Value {} at index {} is not a representable token ID",
+                    value, index
+                )
+            }
+            DecodeError::Overflow { index, value } => {
+                write!(f, "Token ID {} at index {} overflows u32", value, index)
+            }
+            DecodeError::UnknownDynamic { index, value } => {
+                write!(
+                    f,
+                    "Token ID {} at index {} is not in the dynamic vocabulary",
+                    value, index
+                )
+            }
+            DecodeError::FingerprintMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Token space fingerprint mismatch: expected {:#018x}, found {:#018x}",
+                    expected, found
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// A name matched no token or segment of a token space
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownNameError {
+    /// The name that was looked up
+    pub name: String,
+}
+
+impl std::fmt::Display for UnknownNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No token or segment named {:?}", self.name)
+    }
+}
+
+impl std::error::Error for UnknownNameError {}
+
+/// A token was not allowed by a grammar in its current state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnexpectedToken {
+    /// Index of the ID in the sequence
+    pub index: usize,
+    /// The rejected ID
+    pub value: u32,
+    /// Index of the grammar state that rejected it
+    pub state: usize,
+}
+
+impl std::fmt::Display for UnexpectedToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Token ID {} at index {} is not allowed in grammar state {}",
+            self.value, self.index, self.state
+        )
+    }
+}
+
+impl std::error::Error for UnexpectedToken {}
+
+/// Unbalanced start/end tokens found while extracting spans
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SpanError {
+    /// A closing token with no matching opening token before it
+    Unopened {
+        /// Index of the closing ID in the sequence
+        index: usize,
+        /// The closing ID
+        value: u32,
+    },
+    /// A closing token that matches an outer span while an inner one is open
+    Interleaved {
+        /// Index of the closing ID in the sequence
+        index: usize,
+        /// The closing ID
+        value: u32,
+        /// Index of the innermost open span's opening ID
+        open_index: usize,
+        /// The innermost open span's opening ID
+        open_value: u32,
+    },
+    /// An opening token still open at the end of the sequence
+    Unclosed {
+        /// Index of the opening ID in the sequence
+        index: usize,
+        /// The opening ID
+        value: u32,
+    },
+}
+
+impl std::fmt::Display for SpanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpanError::Unopened { index, value } => {
+                write!(
+                    f,
+                    "Token ID {} at index {} closes a span that was never opened",
+                    value, index
+                )
+            }
+            SpanError::Interleaved {
+                index,
+                value,
+                open_index,
+                open_value,
+            } => {
+                write!(
+                    f,
+                    "Token ID {} at index {} closes an outer span while token ID {} at index {} is still open",
+                    value, index, open_value, open_index
+                )
+            }
+            SpanError::Unclosed { index, value } => {
+                write!(
+                    f,
+                    "Token ID {} at index {} opens a span that is never closed",
+                    value, index
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpanError {}
+
+/// Error encoding text into token IDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EncodeError {
+    /// The text contains a special token literal where specials are disallowed
+    DisallowedSpecial {
+        /// Byte offset of the literal in the text
+        offset: usize,
+        /// The literal that was found
+        name: &'static str,
+    },
+    /// A tokenizer ID overflows u32 when shifted past the reserved range
+    Overflow {
+        /// The tokenizer ID
+        value: u32,
+    },
+    /// A typed token is outside its segment, e.g. a range token past its count
+    Token {
+        /// Why the token has no ID in the space
+        error: TokauError,
+    },
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::DisallowedSpecial { offset, name } => {
+                write!(
+                    f,
+                    "Special token {} at byte {} is not allowed in this text",
+                    name, offset
+                )
+            }
+            EncodeError::Overflow { value } => {
+                write!(
+                    f,
+                    "Tokenizer ID {} overflows u32 when shifted past the reserved range",
+                    value
+                )
+            }
+            EncodeError::Token { error } => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// Error parsing token IDs back into chat messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateError {
+    /// The ID does not start a message or is a reserved ID inside message content
+    Unexpected {
+        /// Index of the ID in the sequence
+        index: usize,
+        /// The unexpected ID
+        value: u32,
+    },
+    /// A message is never closed
+    Unterminated {
+        /// Index of the first ID of the message
+        index: usize,
+    },
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::Unexpected { index, value } => {
+                write!(
+                    f,
+                    "Token ID {} at index {} does not fit the chat template",
+                    value, index
+                )
+            }
+            TemplateError::Unterminated { index } => {
+                write!(f, "Message starting at index {} is never closed", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
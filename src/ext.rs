@@ -1,32 +1,210 @@
-use crate::error::TokauError;
-use crate::space::TokenSpace;
+use crate::error::{DecodeError, TokauError};
+use crate::repr::TokenIdRepr;
+use crate::run::Runs;
+use crate::segment::Segmented;
+use crate::space::{OpenSpace, Position, TokenSpace};
+use crate::span::Spans;
+use crate::stream::StreamEvents;
+use crate::token::Token;
 
 // Extension trait for filtering iterables by token type
-pub trait TokenIter: Iterator<Item = u32> + Sized {
+// Most adapters work on token IDs, encode works on tokens and space values.
+// Adapters bounded by TokenIdRepr also accept IDs stored as u16, u64, i64, etc.,
+// reporting values that are not valid u32 IDs instead of truncating them. For
+// u16 and u32 their items are plain values, for other types Results (see
+// `TokenIdRepr::Checked`).
+//
+// It is implemented for every iterator, so importing it adds these methods to
+// all iterators in scope. Each method only applies where its item bounds hold;
+// import it in the modules that work with ids rather than crate-wide.
+pub trait TokenIter: Iterator + Sized {
     /// Filter to remainder values of dynamic tokens (tokens >= RESERVED)
-    fn remainders<S: TokenSpace>(self) -> impl Iterator<Item = u32> {
-        self.filter_map(|id| S::remainder(id))
+    fn remainders<S: TokenSpace>(
+        self,
+    ) -> impl Iterator<Item = <Self::Item as TokenIdRepr>::Checked<u32>>
+    where
+        Self::Item: TokenIdRepr,
+    {
+        self.enumerate()
+            .filter_map(|(index, id)| id.filter_id(index, S::remainder))
     }
 
     /// Decode token IDs to space tokens, returning Result for each conversion
-    fn decode<S: TokenSpace>(self) -> impl Iterator<Item = Result<S, TokauError>> {
-        self.map(|id| S::try_from(id))
+    fn decode<S: TokenSpace>(self) -> impl Iterator<Item = Result<S, TokauError>>
+    where
+        Self::Item: TokenIdRepr,
+    {
+        self.map(|id| id.to_id().and_then(S::try_from))
+    }
+
+    /// Decode all token IDs, stopping at the first failure with its index
+    fn try_decode_all<S: Segmented>(self) -> Result<Vec<S>, DecodeError>
+    where
+        Self::Item: TokenIdRepr,
+    {
+        self.enumerate()
+            .map(|(index, id)| {
+                let id = id.to_id().map_err(|error| DecodeError::at(index, error))?;
+                S::decode_at(index, id)
+            })
+            .collect()
+    }
+
+    /// Decode all token IDs of a sequence stored with the
+    /// [`FINGERPRINT`](Segmented::FINGERPRINT) of the space that wrote it,
+    /// failing before decoding anything if that layout differs from S
+    fn try_decode_fingerprinted<S: Segmented>(self, fingerprint: u64) -> Result<Vec<S>, DecodeError>
+    where
+        Self::Item: TokenIdRepr,
+    {
+        S::verify_fingerprint(fingerprint)?;
+        self.try_decode_all::<S>()
+    }
+
+    /// Decode token IDs, substituting `fallback` for IDs that fail to decode
+    fn decode_lossy<S>(self, fallback: S) -> impl Iterator<Item = S>
+    where
+        Self::Item: TokenIdRepr,
+        S: TokenSpace + Clone,
+    {
+        self.map(move |id| {
+            id.to_id()
+                .and_then(S::try_from)
+                .unwrap_or_else(|_| fallback.clone())
+        })
+    }
+
+    /// Decode token IDs in a space with a dynamic tail, where every ID decodes
+    fn decode_total<S: OpenSpace>(self) -> impl Iterator<Item = S>
+    where
+        Self: Iterator<Item = u32>,
+    {
+        self.map(|id| S::decode_total(id))
     }
 
     /// Shift each value to after the token space's reserved range
     /// This adds RESERVED to each value, placing them in the dynamic token range
-    fn after_reserved<S: TokenSpace>(self) -> impl Iterator<Item = u32> {
-        self.map(|id| S::after_reserved(id))
+    fn after_reserved<S: TokenSpace>(
+        self,
+    ) -> impl Iterator<Item = <Self::Item as TokenIdRepr>::Checked<u32>>
+    where
+        Self::Item: TokenIdRepr,
+    {
+        self.enumerate()
+            .map(|(index, value)| value.map_id(index, S::after_reserved))
+    }
+
+    /// Shift each value to after the reserved range, reporting the index of
+    /// values that would overflow u32 (or the item type) instead of wrapping
+    fn checked_after_reserved<S: TokenSpace>(
+        self,
+    ) -> impl Iterator<Item = Result<Self::Item, DecodeError>>
+    where
+        Self::Item: TokenIdRepr,
+    {
+        self.enumerate().map(|(index, value)| {
+            let value = value
+                .to_id()
+                .map_err(|error| DecodeError::at(index, error))?;
+            let id =
+                S::checked_after_reserved(value).ok_or(DecodeError::Overflow { index, value })?;
+            Self::Item::from_id(id).map_err(|error| DecodeError::at(index, error))
+        })
+    }
+
+    /// Classify token IDs by segment kind without decoding them
+    /// Yields None for IDs outside a space without a dynamic tail
+    fn kinds<S: Segmented>(
+        self,
+    ) -> impl Iterator<Item = <Self::Item as TokenIdRepr>::Checked<Option<S::Kind>>>
+    where
+        Self::Item: TokenIdRepr,
+    {
+        self.enumerate()
+            .map(|(index, id)| id.map_id(index, S::kind_of))
+    }
+
+    /// Filter to tokens of type T, dropping IDs from other segments
+    fn only<S, T>(self) -> impl Iterator<Item = <Self::Item as TokenIdRepr>::Checked<T>>
+    where
+        Self::Item: TokenIdRepr,
+        S: TokenSpace + Position<T>,
+        T: Token + TryFrom<u32, Error = TokauError>,
+    {
+        self.enumerate()
+            .filter_map(|(index, id)| id.filter_id(index, S::try_as::<T>))
+    }
+
+    /// Indices in the stream of IDs that belong to token type T
+    fn positions_of<S, T>(self) -> impl Iterator<Item = <Self::Item as TokenIdRepr>::Checked<usize>>
+    where
+        Self::Item: TokenIdRepr,
+        S: TokenSpace + Position<T>,
+        T: Token,
+    {
+        self.enumerate().filter_map(|(index, id)| {
+            id.filter_id(index, |id| {
+                id.checked_sub(<S as Position<T>>::OFFSET)
+                    .filter(|local| *local < T::COUNT)
+                    .map(|_| index)
+            })
+        })
+    }
+
+    /// Split token IDs into (reserved, dynamic) IDs, keeping the order within each
+    fn partition_reserved<S: TokenSpace>(self) -> (Vec<u32>, Vec<u32>)
+    where
+        Self: Iterator<Item = u32>,
+    {
+        self.partition(|&id| S::is_reserved(id))
+    }
+
+    /// Encode tokens or space values to their global IDs in space S
+    fn encode<S>(self) -> impl Iterator<Item = u32>
+    where
+        Self::Item: Into<S>,
+        S: TokenSpace + Copy,
+    {
+        self.map(|item| item.into().value())
+    }
+
+    /// Extract spans between declared start/end pairs (`#[pair(Open, Close)]`),
+    /// reporting unbalanced or interleaved pairs by index
+    fn spans<S: Segmented>(self) -> Spans<Self, S>
+    where
+        Self: Iterator<Item = u32>,
+    {
+        Spans::new(self)
+    }
+
+    /// Decode ids incrementally into stream events (segment changes, tokens,
+    /// span opens/closes and dynamic runs), see [`StreamDecoder`]
+    ///
+    /// [`StreamDecoder`]: crate::StreamDecoder
+    fn stream<S: Segmented>(self) -> StreamEvents<Self, S>
+    where
+        Self: Iterator<Item = u32>,
+    {
+        StreamEvents::new(self)
+    }
+
+    /// Split ids into reserved tokens and runs of dynamic remainders, keeping
+    /// order, e.g. to detokenize each run and render the specials in between
+    fn runs<S: OpenSpace>(self) -> Runs<Self, S>
+    where
+        Self: Iterator<Item = u32>,
+    {
+        Runs::new(self)
     }
 }
 
-// Implementation for all iterators over u32
-impl<I: Iterator<Item = u32> + Sized> TokenIter for I {}
+// Implementation for all iterators
+impl<I: Iterator + Sized> TokenIter for I {}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::space::tests::DynamicGingerSpace;
+    use crate::space::tests::{DynamicGingerSpace, GingerSpace};
     use crate::token::tests::*;
 
     #[test]
@@ -174,7 +352,7 @@ mod tests {
         assert_eq!(empty_result, vec![]);
 
         // All tokens in dynamic range (no upper bounds)
-        let out_of_range = vec![2000, 3000, 4000];
+        let out_of_range = vec![2000u32, 3000, 4000];
         let remainder_values: Vec<u32> = out_of_range
             .clone()
             .into_iter()
@@ -201,7 +379,7 @@ mod tests {
         }
 
         // Boundary cases
-        let boundary = vec![1009, 1010, 1509, 1510]; // Last static, first dynamic, dynamic tokens
+        let boundary = vec![1009u32, 1010, 1509, 1510]; // Last static, first dynamic, dynamic tokens
         let remainder_boundary: Vec<u32> = boundary
             .clone()
             .into_iter()
@@ -209,4 +387,218 @@ mod tests {
             .collect();
         assert_eq!(remainder_boundary, vec![0, 499, 500]); // Remainder values (excluding 1009 which is static)
     }
+
+    #[test]
+    fn test_decode_total() {
+        let tokens: Vec<u32> = vec![0, 5, 9, 10, 1010, 2000];
+
+        let decoded: Vec<DynamicGingerSpace> = tokens
+            .clone()
+            .into_iter()
+            .decode_total::<DynamicGingerSpace>()
+            .collect();
+        assert_eq!(
+            decoded,
+            vec![
+                DynamicGingerSpace::Ginger(GingerToken::TextStart),
+                DynamicGingerSpace::Mao(MaoToken::ProgramStart),
+                DynamicGingerSpace::Single(SingleToken::Single),
+                DynamicGingerSpace::Text(TextTokens(0)),
+                DynamicGingerSpace::Dynamic(0),
+                DynamicGingerSpace::Dynamic(990),
+            ]
+        );
+
+        // Same tokens as the fallible decode, without the Result wrapping
+        let fallible: Vec<DynamicGingerSpace> = tokens
+            .into_iter()
+            .decode::<DynamicGingerSpace>()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(decoded, fallible);
+    }
+
+    #[test]
+    fn test_only() {
+        let tokens: Vec<u32> = vec![0, 5, 6, 7, 10, 50, 1010, 8];
+
+        let mao_tokens: Vec<MaoToken> = tokens
+            .clone()
+            .into_iter()
+            .only::<DynamicGingerSpace, MaoToken>()
+            .collect();
+        assert_eq!(
+            mao_tokens,
+            vec![
+                MaoToken::ProgramStart,
+                MaoToken::ProgramEnd,
+                MaoToken::Fn,
+                MaoToken::Struct,
+            ]
+        );
+
+        let text_tokens: Vec<TextTokens> = tokens
+            .into_iter()
+            .only::<DynamicGingerSpace, TextTokens>()
+            .collect();
+        assert_eq!(text_tokens, vec![TextTokens(0), TextTokens(40)]);
+    }
+
+    #[test]
+    fn test_positions_of() {
+        let tokens: Vec<u32> = vec![0, 5, 1010, 7, 10, 8, 9];
+
+        let mao_positions: Vec<usize> = tokens
+            .clone()
+            .into_iter()
+            .positions_of::<DynamicGingerSpace, MaoToken>()
+            .collect();
+        assert_eq!(mao_positions, vec![1, 3, 5]);
+
+        let single_positions: Vec<usize> = tokens
+            .into_iter()
+            .positions_of::<DynamicGingerSpace, SingleToken>()
+            .collect();
+        assert_eq!(single_positions, vec![6]);
+    }
+
+    #[test]
+    fn test_encode_hand_written_space() {
+        let ids: Vec<u32> = vec![
+            DynamicGingerSpace::Mao(MaoToken::Fn),
+            DynamicGingerSpace::Text(TextTokens(3)),
+            DynamicGingerSpace::Dynamic(2),
+        ]
+        .into_iter()
+        .encode::<DynamicGingerSpace>()
+        .collect();
+        assert_eq!(ids, vec![7, 13, 1012]);
+    }
+
+    #[test]
+    fn test_partition_reserved() {
+        let tokens: Vec<u32> = vec![1010, 0, 1500, 1009, 7, 2000];
+
+        let (reserved, dynamic) = tokens
+            .into_iter()
+            .partition_reserved::<DynamicGingerSpace>();
+        assert_eq!(reserved, vec![0, 1009, 7]);
+        assert_eq!(dynamic, vec![1010, 1500, 2000]);
+
+        let (reserved, dynamic) = Vec::new()
+            .into_iter()
+            .partition_reserved::<DynamicGingerSpace>();
+        assert!(reserved.is_empty());
+        assert!(dynamic.is_empty());
+    }
+
+    #[test]
+    fn test_try_decode_all() {
+        let decoded = vec![0, 5, 1009].into_iter().try_decode_all::<GingerSpace>();
+        assert_eq!(
+            decoded,
+            Ok(vec![
+                GingerSpace::Ginger(GingerToken::TextStart),
+                GingerSpace::Mao(MaoToken::ProgramStart),
+                GingerSpace::Text(TextTokens(999)),
+            ])
+        );
+
+        // The first bad ID is reported with its index in the stream
+        let decoded = vec![0, 5, 1010, 9, 2000]
+            .into_iter()
+            .try_decode_all::<GingerSpace>();
+        assert_eq!(
+            decoded,
+            Err(DecodeError::OutOfRange {
+                index: 2,
+                value: 1010,
+                max: 1010,
+                nearest: Some("Text"),
+            })
+        );
+        assert_eq!(
+            decoded.unwrap_err().to_string(),
+            "Token ID 1010 at index 2 is out of valid range [0, 1010), past segment Text"
+        );
+
+        // Open spaces never fail
+        let decoded = vec![0, 1010, u32::MAX]
+            .into_iter()
+            .try_decode_all::<DynamicGingerSpace>();
+        assert_eq!(decoded.map(|tokens| tokens.len()), Ok(3));
+    }
+
+    #[test]
+    fn test_try_decode_fingerprinted() {
+        let ids = vec![0, 5, 1010];
+        assert_eq!(
+            ids.clone()
+                .into_iter()
+                .try_decode_fingerprinted::<DynamicGingerSpace>(DynamicGingerSpace::FINGERPRINT)
+                .map(|tokens| tokens.len()),
+            Ok(3)
+        );
+
+        // Ids written by a differently shaped space are rejected up front
+        assert_eq!(
+            ids.into_iter()
+                .try_decode_fingerprinted::<DynamicGingerSpace>(GingerSpace::FINGERPRINT),
+            Err(DecodeError::FingerprintMismatch {
+                expected: DynamicGingerSpace::FINGERPRINT,
+                found: GingerSpace::FINGERPRINT,
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_lossy() {
+        let fallback = GingerSpace::Single(SingleToken::Single);
+        let decoded: Vec<GingerSpace> = vec![0, 5000, 7, 1010]
+            .into_iter()
+            .decode_lossy(fallback)
+            .collect();
+        assert_eq!(
+            decoded,
+            vec![
+                GingerSpace::Ginger(GingerToken::TextStart),
+                fallback,
+                GingerSpace::Mao(MaoToken::Fn),
+                fallback,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checked_after_reserved() {
+        let shifted: Vec<Result<u32, DecodeError>> = vec![0, 100, u32::MAX - 1010, u32::MAX]
+            .into_iter()
+            .checked_after_reserved::<DynamicGingerSpace>()
+            .collect();
+        assert_eq!(
+            shifted,
+            vec![
+                Ok(1010),
+                Ok(1110),
+                Ok(u32::MAX),
+                Err(DecodeError::Overflow {
+                    index: 3,
+                    value: u32::MAX,
+                }),
+            ]
+        );
+
+        // Collecting stops at the first overflow
+        let collected: Result<Vec<u32>, DecodeError> = vec![1, u32::MAX - 5, 2]
+            .into_iter()
+            .checked_after_reserved::<DynamicGingerSpace>()
+            .collect();
+        assert_eq!(
+            collected,
+            Err(DecodeError::Overflow {
+                index: 1,
+                value: u32::MAX - 5,
+            })
+        );
+    }
 }
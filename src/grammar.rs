@@ -0,0 +1,371 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::{TokauError, UnexpectedToken, UnknownNameError};
+use crate::segment::Segmented;
+use crate::set::TokenSet;
+use crate::space::Position;
+use crate::token::Token;
+
+// Source of the brand telling states of different grammars apart
+static NEXT_GRAMMAR: AtomicU64 = AtomicU64::new(0);
+
+/// State of a [`Grammar`], only valid in the grammar whose builder added it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateId {
+    grammar: u64,
+    index: usize,
+}
+
+impl StateId {
+    /// Index of the state in the order it was added
+    pub fn index(self) -> usize {
+        self.index
+    }
+}
+
+struct Transition<S> {
+    on: TokenSet<S>,
+    to: StateId,
+}
+
+struct State<S> {
+    transitions: Vec<Transition<S>>,
+    // Union of the transition sets, precomputed for masking
+    allowed: TokenSet<S>,
+    accepting: bool,
+}
+
+/// Finite automaton over a space's token types and individual tokens, for
+/// constraining generation (e.g. only audio tokens between `AudioStart` and
+/// `AudioEnd`).
+///
+/// Transitions are labelled with token sets and checked in the order they were
+/// added, so the first matching transition wins.
+pub struct Grammar<S> {
+    id: u64,
+    states: Vec<State<S>>,
+    start: StateId,
+}
+
+impl<S: Segmented> Grammar<S> {
+    /// Start building a grammar
+    pub fn builder() -> GrammarBuilder<S> {
+        GrammarBuilder {
+            id: NEXT_GRAMMAR.fetch_add(1, Ordering::Relaxed),
+            states: Vec::new(),
+        }
+    }
+
+    /// Matcher positioned at the start state
+    pub fn matcher(&self) -> Matcher<'_, S> {
+        Matcher {
+            grammar: self,
+            state: self.start,
+            index: 0,
+        }
+    }
+
+    /// State reached from `state` by `id`, `None` if the id is not allowed
+    /// there or `state` belongs to another grammar
+    pub fn next(&self, state: StateId, id: u32) -> Option<StateId> {
+        self.state(state)?
+            .transitions
+            .iter()
+            .find(|transition| transition.on.contains(id))
+            .map(|transition| transition.to)
+    }
+
+    /// Ids allowed in `state`, `None` if it belongs to another grammar
+    pub fn allowed(&self, state: StateId) -> Option<&TokenSet<S>> {
+        self.state(state).map(|state| &state.allowed)
+    }
+
+    /// Check if a sequence may end in `state`, false if it belongs to another
+    /// grammar
+    pub fn is_accepting(&self, state: StateId) -> bool {
+        self.state(state).is_some_and(|state| state.accepting)
+    }
+
+    fn state(&self, state: StateId) -> Option<&State<S>> {
+        if state.grammar != self.id {
+            return None;
+        }
+        self.states.get(state.index)
+    }
+}
+
+/// Builder for a [`Grammar`]. States are added first, then connected by
+/// transitions on token types, individual tokens or arbitrary token sets.
+///
+/// Each builder brands the states it adds, so passing a state of another
+/// grammar panics instead of silently naming one of this grammar's states.
+pub struct GrammarBuilder<S> {
+    id: u64,
+    states: Vec<(Vec<Transition<S>>, bool)>,
+}
+
+impl<S: Segmented> GrammarBuilder<S> {
+    /// Add a state that is not accepting
+    pub fn state(&mut self) -> StateId {
+        self.add_state(false)
+    }
+
+    /// Add a state where a sequence may end
+    pub fn accepting_state(&mut self) -> StateId {
+        self.add_state(true)
+    }
+
+    /// Move from `from` to `to` on any id in `on`
+    ///
+    /// # Panics
+    ///
+    /// If `from` or `to` was added by another builder.
+    pub fn on_set(&mut self, from: StateId, on: TokenSet<S>, to: StateId) -> &mut Self {
+        self.check(to);
+        self.check(from);
+        self.states[from.index].0.push(Transition { on, to });
+        self
+    }
+
+    /// Move from `from` to `to` on any token of type T
+    pub fn on_segment<T: Token>(&mut self, from: StateId, to: StateId) -> &mut Self
+    where
+        S: Position<T>,
+    {
+        self.on_set(from, TokenSet::segment::<T>(), to)
+    }
+
+    /// Move from `from` to `to` on a single token, failing if it is outside
+    /// its segment
+    pub fn on_token<T: Token>(
+        &mut self,
+        from: StateId,
+        token: T,
+        to: StateId,
+    ) -> Result<&mut Self, TokauError>
+    where
+        S: Position<T>,
+    {
+        Ok(self.on_set(from, TokenSet::empty().with_token(token)?, to))
+    }
+
+    /// Move from `from` to `to` on any dynamic id
+    pub fn on_dynamic(&mut self, from: StateId, to: StateId) -> &mut Self {
+        self.on_set(from, TokenSet::dynamic(), to)
+    }
+
+    /// Move from `from` to `to` on any id in the segment of `kind`
+    pub fn on_kind(&mut self, from: StateId, kind: S::Kind, to: StateId) -> &mut Self {
+        let set = match S::segment(kind) {
+            Some(segment) => TokenSet::from_range(segment.range()),
+            None => TokenSet::empty().with_dynamic(S::DYNAMIC == Some(kind)),
+        };
+        self.on_set(from, set, to)
+    }
+
+    /// Move from `from` to `to` on the token with this name
+    pub fn on_name(
+        &mut self,
+        from: StateId,
+        name: &str,
+        to: StateId,
+    ) -> Result<&mut Self, UnknownNameError> {
+        let id = S::id_of_name(name).ok_or_else(|| UnknownNameError {
+            name: name.to_string(),
+        })?;
+        Ok(self.on_set(from, TokenSet::from_ids([id]), to))
+    }
+
+    /// Finish the grammar, starting in `start`
+    ///
+    /// # Panics
+    ///
+    /// If `start` was added by another builder.
+    pub fn build(self, start: StateId) -> Grammar<S> {
+        self.check(start);
+        let states = self
+            .states
+            .into_iter()
+            .map(|(transitions, accepting)| State {
+                allowed: transitions
+                    .iter()
+                    .fold(TokenSet::empty(), |allowed, transition| {
+                        allowed.union(&transition.on)
+                    }),
+                transitions,
+                accepting,
+            })
+            .collect();
+        Grammar {
+            id: self.id,
+            states,
+            start,
+        }
+    }
+
+    fn add_state(&mut self, accepting: bool) -> StateId {
+        self.states.push((Vec::new(), accepting));
+        StateId {
+            grammar: self.id,
+            index: self.states.len() - 1,
+        }
+    }
+
+    fn check(&self, state: StateId) {
+        assert!(
+            state.grammar == self.id,
+            "state {} belongs to another grammar",
+            state.index
+        );
+    }
+}
+
+/// Position of a sequence in a [`Grammar`], stepped with each emitted id
+pub struct Matcher<'g, S> {
+    grammar: &'g Grammar<S>,
+    state: StateId,
+    index: usize,
+}
+
+impl<'g, S: Segmented> Matcher<'g, S> {
+    /// Current state
+    pub fn state(&self) -> StateId {
+        self.state
+    }
+
+    /// Ids allowed next, for masking logits
+    pub fn allowed(&self) -> &'g TokenSet<S> {
+        // The matcher only holds states of its own grammar
+        &self.grammar.states[self.state.index].allowed
+    }
+
+    /// Check if the sequence so far may end here
+    pub fn is_accepting(&self) -> bool {
+        self.grammar.states[self.state.index].accepting
+    }
+
+    /// Advance by one emitted id. A rejected id leaves the matcher unchanged.
+    pub fn step(&mut self, id: u32) -> Result<StateId, UnexpectedToken> {
+        let next = self.grammar.next(self.state, id).ok_or(UnexpectedToken {
+            index: self.index,
+            value: id,
+            state: self.state.index,
+        })?;
+        self.state = next;
+        self.index += 1;
+        Ok(next)
+    }
+
+    /// Advance by every id in a sequence, stopping at the first rejected one
+    pub fn step_all(
+        &mut self,
+        ids: impl IntoIterator<Item = u32>,
+    ) -> Result<StateId, UnexpectedToken> {
+        for id in ids {
+            self.step(id)?;
+        }
+        Ok(self.state)
+    }
+
+    /// Return to the start state
+    pub fn reset(&mut self) {
+        self.state = self.grammar.start;
+        self.index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::tests::{DynamicGingerSpace, DynamicGingerSpaceKind};
+    use crate::token::tests::*;
+
+    // Text and dynamic ids freely, Mao programs between ProgramStart and ProgramEnd
+    fn grammar() -> Grammar<DynamicGingerSpace> {
+        let mut builder = Grammar::<DynamicGingerSpace>::builder();
+        let free = builder.accepting_state();
+        let program = builder.state();
+        builder
+            .on_segment::<TextTokens>(free, free)
+            .on_dynamic(free, free)
+            .on_token(free, MaoToken::ProgramStart, program)
+            .unwrap()
+            .on_token(program, MaoToken::Fn, program)
+            .unwrap()
+            .on_token(program, MaoToken::Struct, program)
+            .unwrap()
+            .on_kind(program, DynamicGingerSpaceKind::Text, program)
+            .on_token(program, MaoToken::ProgramEnd, free)
+            .unwrap();
+        builder.build(free)
+    }
+
+    #[test]
+    fn test_allowed_sets() {
+        let grammar = grammar();
+        let mut matcher = grammar.matcher();
+        assert!(matcher.is_accepting());
+        assert!(matcher.allowed().contains(10));
+        assert!(matcher.allowed().contains(5000));
+        assert!(matcher.allowed().contains(5));
+        assert!(!matcher.allowed().contains(6));
+        assert!(!matcher.allowed().contains(0));
+
+        matcher.step(5).unwrap();
+        assert!(!matcher.is_accepting());
+        assert_eq!(
+            matcher.allowed().ids().take(4).collect::<Vec<_>>(),
+            vec![6, 7, 8, 10]
+        );
+        assert!(!matcher.allowed().contains(5000));
+
+        matcher.step_all([7, 12, 8, 6]).unwrap();
+        assert!(matcher.is_accepting());
+    }
+
+    #[test]
+    fn test_rejected_ids() {
+        let grammar = grammar();
+        let mut matcher = grammar.matcher();
+        let program = matcher.step_all([10, 5]).unwrap();
+
+        assert_eq!(
+            matcher.step(5000),
+            Err(UnexpectedToken {
+                index: 2,
+                value: 5000,
+                state: program.index(),
+            })
+        );
+        // State is unchanged after a rejection
+        assert_eq!(matcher.state(), program);
+        assert_eq!(matcher.step(6).map(StateId::index), Ok(0));
+
+        matcher.reset();
+        assert!(matcher.is_accepting());
+        assert!(matcher.step(0).is_err());
+    }
+
+    #[test]
+    fn test_foreign_states() {
+        let grammar = grammar();
+        let mut other = Grammar::<DynamicGingerSpace>::builder();
+        let foreign = other.state();
+
+        // Same index as the start state, but not a state of this grammar
+        assert_eq!(foreign.index(), grammar.matcher().state().index());
+        assert_eq!(grammar.next(foreign, 10), None);
+        assert!(grammar.allowed(foreign).is_none());
+        assert!(!grammar.is_accepting(foreign));
+        assert!(grammar.allowed(grammar.matcher().state()).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "state 0 belongs to another grammar")]
+    fn test_foreign_state_in_builder() {
+        let mut builder = Grammar::<DynamicGingerSpace>::builder();
+        let state = builder.state();
+        let foreign = Grammar::<DynamicGingerSpace>::builder().state();
+        builder.on_dynamic(state, foreign);
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::error::{DecodeError, TokauError};
+use crate::segment::Segmented;
+use crate::sequence::checked_pair;
+use crate::space::Position;
+use crate::span::{SpanStack, SpanStep};
+use crate::token::Token;
+
+/// Label of positions excluded from the loss, as expected by PyTorch's
+/// `CrossEntropyLoss`
+pub const IGNORE_INDEX: i64 = -100;
+
+/// How a position contributes to the training loss
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Loss {
+    /// Set the label to [`IGNORE_INDEX`] and the weight to 0
+    Ignore,
+    /// Keep the label, scaling the loss by this weight
+    Weight(f32),
+}
+
+enum Selector<K> {
+    Ids(Range<u32>),
+    Kind(K),
+    // Opening id of a declared pair, spans include both ends
+    Span(u32),
+}
+
+/// Rules deciding which positions of a sequence are trained on, and how much.
+///
+/// Rules are keyed by token type, segment kind, single token or span, and the
+/// last rule matching a position wins. Positions no rule matches get the
+/// default, [`Loss::Weight(1.0)`](Loss::Weight) unless changed.
+pub struct LossPolicy<S: Segmented> {
+    default: Loss,
+    rules: Vec<(Selector<S::Kind>, Loss)>,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: Segmented> LossPolicy<S> {
+    /// Policy training every position with weight 1
+    pub fn new() -> Self {
+        LossPolicy {
+            default: Loss::Weight(1.0),
+            rules: Vec::new(),
+            _space: PhantomData,
+        }
+    }
+
+    /// Use `loss` for positions no rule matches, e.g. [`Loss::Ignore`] to
+    /// train only on what later rules select
+    pub fn with_default(mut self, loss: Loss) -> Self {
+        self.default = loss;
+        self
+    }
+
+    /// Apply `loss` to every token of type T
+    pub fn segment<T: Token>(mut self, loss: Loss) -> Self
+    where
+        S: Position<T>,
+    {
+        let offset = <S as Position<T>>::OFFSET;
+        self.rules
+            .push((Selector::Ids(offset..offset + T::COUNT), loss));
+        self
+    }
+
+    /// Apply `loss` to every token in the segment of `kind`, including the
+    /// dynamic tail
+    pub fn kind(mut self, kind: S::Kind, loss: Loss) -> Self {
+        self.rules.push((Selector::Kind(kind), loss));
+        self
+    }
+
+    /// Apply `loss` to a single token
+    pub fn token<T: Token>(mut self, token: T, loss: Loss) -> Self
+    where
+        S: Position<T>,
+    {
+        let id = S::position_of(token);
+        self.rules.push((Selector::Ids(id..id + 1), loss));
+        self
+    }
+
+    /// Apply `loss` to every position from an `open` token to its matching
+    /// `close` token, both included. A span left open runs to the end.
+    ///
+    /// # Panics
+    ///
+    /// If `open` and `close` are not a pair declared with `#[pair(Open, Close)]`
+    /// or are outside their segments, see [`try_span`](Self::try_span).
+    pub fn span<A: Token, B: Token>(self, open: A, close: B, loss: Loss) -> Self
+    where
+        S: Position<A> + Position<B>,
+    {
+        self.try_span(open, close, loss)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Apply `loss` to a span like [`span`](Self::span), or fail if `open` and
+    /// `close` are not a declared pair. Spans are matched like
+    /// [`SequenceBuilder::span`](crate::SequenceBuilder::span) writes them,
+    /// so only declared pairs are accepted.
+    pub fn try_span<A: Token, B: Token>(
+        mut self,
+        open: A,
+        close: B,
+        loss: Loss,
+    ) -> Result<Self, TokauError>
+    where
+        S: Position<A> + Position<B>,
+    {
+        let (open, _) = checked_pair::<S, A, B>(open, close)?;
+        self.rules.push((Selector::Span(open), loss));
+        Ok(self)
+    }
+
+    /// Labels and loss weights for `ids`. Labels are the ids themselves, not
+    /// shifted for next-token prediction.
+    pub fn labels(&self, ids: &[u32]) -> Result<(Vec<i64>, Vec<f32>), DecodeError> {
+        let mut losses = vec![self.default; ids.len()];
+        for (index, &id) in ids.iter().enumerate() {
+            S::decode_at(index, id)?;
+        }
+
+        for (selector, loss) in &self.rules {
+            match selector {
+                Selector::Ids(range) => {
+                    for (index, id) in ids.iter().enumerate() {
+                        if range.contains(id) {
+                            losses[index] = *loss;
+                        }
+                    }
+                }
+                Selector::Kind(kind) => {
+                    for (index, &id) in ids.iter().enumerate() {
+                        if S::kind_of(id) == Some(*kind) {
+                            losses[index] = *loss;
+                        }
+                    }
+                }
+                // Spans are matched like Spans and StreamDecoder match them, so
+                // a stray close is left alone and an interleaved close ends
+                // the spans opened inside it
+                Selector::Span(open) => {
+                    let mut stack = SpanStack::default();
+                    for (index, &id) in ids.iter().enumerate() {
+                        let closes = match stack.push::<S>(index, id) {
+                            SpanStep::Close { open_value, .. } => open_value == *open,
+                            _ => false,
+                        };
+                        if closes || stack.is_open(*open) {
+                            losses[index] = *loss;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(ids
+            .iter()
+            .zip(losses)
+            .map(|(&id, loss)| match loss {
+                Loss::Ignore => (IGNORE_INDEX, 0.0),
+                Loss::Weight(weight) => (i64::from(id), weight),
+            })
+            .unzip())
+    }
+}
+
+impl<S: Segmented> Default for LossPolicy<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Segmented> fmt::Debug for LossPolicy<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LossPolicy")
+            .field("default", &self.default)
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::tests::{DynamicGingerSpace, DynamicGingerSpaceKind, GingerSpace};
+    use crate::token::tests::*;
+
+    #[test]
+    fn test_rules() {
+        let policy = LossPolicy::<DynamicGingerSpace>::new()
+            .segment::<MaoToken>(Loss::Ignore)
+            .kind(DynamicGingerSpaceKind::Dynamic, Loss::Weight(0.5))
+            .token(MaoToken::ProgramEnd, Loss::Weight(2.0));
+
+        let (labels, weights) = policy.labels(&[5, 1010, 6, 9]).unwrap();
+        assert_eq!(labels, vec![-100, 1010, 6, 9]);
+        assert_eq!(weights, vec![0.0, 0.5, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn test_spans() {
+        // Train only inside audio spans, nested text included
+        let policy = LossPolicy::<DynamicGingerSpace>::new()
+            .with_default(Loss::Ignore)
+            .span(
+                GingerToken::AudioStart,
+                GingerToken::AudioEnd,
+                Loss::Weight(1.0),
+            );
+
+        let ids = [1010, 2, 1011, 0, 1012, 1, 3, 1013, 3];
+        let (labels, _) = policy.labels(&ids).unwrap();
+        assert_eq!(labels, vec![-100, 2, 1011, 0, 1012, 1, 3, -100, -100]);
+
+        // Closing the audio span ends the text span interleaved inside it,
+        // as TokenIter::spans reports it
+        let policy = LossPolicy::<DynamicGingerSpace>::new()
+            .with_default(Loss::Ignore)
+            .span(
+                GingerToken::TextStart,
+                GingerToken::TextEnd,
+                Loss::Weight(1.0),
+            );
+        let (labels, _) = policy.labels(&[2, 0, 1010, 3, 1011, 1]).unwrap();
+        assert_eq!(labels, vec![-100, 0, 1010, -100, -100, -100]);
+    }
+
+    #[test]
+    fn test_span_not_a_pair() {
+        let policy = LossPolicy::<DynamicGingerSpace>::new().try_span(
+            GingerToken::TextStart,
+            GingerToken::AudioEnd,
+            Loss::Ignore,
+        );
+        assert_eq!(
+            policy.map(|_| ()),
+            Err(TokauError::Unpaired { open: 0, close: 3 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_id() {
+        let policy = LossPolicy::<GingerSpace>::new();
+        assert!(matches!(
+            policy.labels(&[0, 1010]),
+            Err(DecodeError::OutOfRange { index: 1, .. })
+        ));
+    }
+}
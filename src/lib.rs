@@ -1,15 +1,58 @@
+pub mod bulk;
+pub mod codec;
 pub mod default;
 pub mod error;
 pub mod ext;
+pub mod grammar;
+pub mod labels;
+pub mod logits;
+pub mod pack;
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod positions;
+pub mod render;
+pub mod repr;
+pub mod run;
+pub mod sample;
+pub mod segment;
+pub mod sequence;
+pub mod set;
 pub mod space;
+pub mod span;
+pub mod special;
+pub mod stream;
+pub mod template;
 pub mod token;
+pub mod truncate;
 
 // Re-export main types for convenience
+pub use bulk::{SegmentIndex, SegmentTable};
+pub use codec::{ByteTokenizer, Codec, Tokenizer};
 pub use default::DefaultTokenSpace;
-pub use error::TokauError;
+pub use error::{
+    DecodeError, EncodeError, SpanError, TemplateError, TokauError, UnexpectedToken,
+    UnknownNameError,
+};
 pub use ext::TokenIter;
-pub use space::{Position, TokenSpace};
+pub use grammar::{Grammar, GrammarBuilder, Matcher, StateId};
+pub use labels::{IGNORE_INDEX, Loss, LossPolicy};
+pub use logits::LogitBias;
+pub use pack::{PackedRow, Packer};
+pub use positions::{PositionIds, PositionRules, Step};
+pub use render::{DynamicVocab, Render};
+pub use repr::TokenIdRepr;
+pub use run::Run;
+pub use sample::Sampler;
+pub use segment::{Segment, Segmented};
+pub use sequence::SequenceBuilder;
+pub use set::TokenSet;
+pub use space::{ClosedSpace, OpenSpace, Position, TokenSpace};
+pub use span::Span;
+pub use special::{SpecialEncoder, Specials};
+pub use stream::{StreamDecoder, StreamEvent};
+pub use template::{ChatRole, ChatTemplate, Frame, Message};
 pub use token::Token;
+pub use truncate::{Chunk, SpanPolicy, Truncator};
 
 // Re-export derive macros when feature is enabled
 #[cfg(feature = "derive")]
@@ -0,0 +1,315 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::error::UnknownNameError;
+use crate::segment::Segmented;
+use crate::set::TokenSet;
+use crate::space::{Position, TokenSpace};
+use crate::token::Token;
+
+/// Set every logit outside `allowed` to negative infinity.
+///
+/// Works a bitset word at a time, so fully allowed or fully disallowed blocks of
+/// 64 ids are handled without checking individual bits.
+pub fn mask<S: Segmented>(logits: &mut [f32], allowed: &TokenSet<S>) {
+    for (index, &word) in allowed.words().iter().enumerate() {
+        let start = index as u32 * 64;
+        let block = clamp(start..(start + 64).min(S::RESERVED), logits.len());
+        match word {
+            u64::MAX => {}
+            0 => logits[block].fill(f32::NEG_INFINITY),
+            _ => {
+                for (bit, logit) in logits[block].iter_mut().enumerate() {
+                    if word & (1 << bit) == 0 {
+                        *logit = f32::NEG_INFINITY;
+                    }
+                }
+            }
+        }
+    }
+    if !allowed.contains_dynamic() {
+        fill(logits, S::RESERVED..u32::MAX, f32::NEG_INFINITY);
+    }
+}
+
+/// Disallow every token of type T
+pub fn mask_segment<S, T>(logits: &mut [f32])
+where
+    S: TokenSpace + Position<T>,
+    T: Token,
+{
+    fill(logits, segment_range::<S, T>(), f32::NEG_INFINITY);
+}
+
+/// Disallow every token in the segment of `kind`, including the dynamic tail
+pub fn mask_kind<S: Segmented>(logits: &mut [f32], kind: S::Kind) {
+    fill(logits, kind_range::<S>(kind), f32::NEG_INFINITY);
+}
+
+/// Add `bias` to every token of type T
+pub fn bias_segment<S, T>(logits: &mut [f32], bias: f32)
+where
+    S: TokenSpace + Position<T>,
+    T: Token,
+{
+    add(logits, segment_range::<S, T>(), bias);
+}
+
+/// Add `bias` to every token in the segment of `kind`, including the dynamic tail
+pub fn bias_kind<S: Segmented>(logits: &mut [f32], kind: S::Kind, bias: f32) {
+    add(logits, kind_range::<S>(kind), bias);
+}
+
+/// Add `bias` to a single token
+pub fn bias_token<S, T>(logits: &mut [f32], token: T, bias: f32)
+where
+    S: TokenSpace + Position<T>,
+    T: Token,
+{
+    let id = S::position_of(token);
+    add(logits, id..id + 1, bias);
+}
+
+/// Additive biases over segments and tokens of a space, applied in one pass
+/// per entry.
+///
+/// Entries stack, so a token inside a biased segment can get its own extra bias.
+pub struct LogitBias<S> {
+    entries: Vec<(Range<u32>, f32)>,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: Segmented> LogitBias<S> {
+    /// Bias config with no entries
+    pub fn new() -> Self {
+        LogitBias {
+            entries: Vec::new(),
+            _space: PhantomData,
+        }
+    }
+
+    /// Bias every token of type T
+    pub fn segment<T: Token>(mut self, bias: f32) -> Self
+    where
+        S: Position<T>,
+    {
+        self.entries.push((segment_range::<S, T>(), bias));
+        self
+    }
+
+    /// Bias every token in the segment of `kind`, including the dynamic tail
+    pub fn kind(mut self, kind: S::Kind, bias: f32) -> Self {
+        self.entries.push((kind_range::<S>(kind), bias));
+        self
+    }
+
+    /// Bias a single token
+    pub fn token<T: Token>(mut self, token: T, bias: f32) -> Self
+    where
+        S: Position<T>,
+    {
+        let id = S::position_of(token);
+        self.entries.push((id..id + 1, bias));
+        self
+    }
+
+    /// Bias a token or segment by name. Tokens match their name or variant name
+    /// (e.g. `"<|im_end|>"` or `"ImEnd"`) and are checked before segments, which
+    /// match the space variant (e.g. `"Control"`, or the dynamic `"Vocab"`).
+    pub fn named(mut self, name: &str, bias: f32) -> Result<Self, UnknownNameError> {
+        let range = if let Some(id) = S::id_of_name(name) {
+            id..id + 1
+        } else if let Some(kind) = S::kind_named(name) {
+            kind_range::<S>(kind)
+        } else {
+            return Err(UnknownNameError {
+                name: name.to_string(),
+            });
+        };
+        self.entries.push((range, bias));
+        Ok(self)
+    }
+
+    /// Load a bias config from `(name, bias)` pairs, e.g. parsed from a config file
+    pub fn from_names<N: AsRef<str>>(
+        entries: impl IntoIterator<Item = (N, f32)>,
+    ) -> Result<Self, UnknownNameError> {
+        entries
+            .into_iter()
+            .try_fold(Self::new(), |config, (name, bias)| {
+                config.named(name.as_ref(), bias)
+            })
+    }
+
+    /// Add every entry's bias to the logits
+    pub fn apply(&self, logits: &mut [f32]) {
+        for (range, bias) in &self.entries {
+            add(logits, range.clone(), *bias);
+        }
+    }
+}
+
+impl<S: Segmented> Default for LogitBias<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Clone for LogitBias<S> {
+    fn clone(&self) -> Self {
+        LogitBias {
+            entries: self.entries.clone(),
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<S> fmt::Debug for LogitBias<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogitBias")
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+fn segment_range<S: Position<T>, T: Token>() -> Range<u32> {
+    let offset = <S as Position<T>>::OFFSET;
+    offset..offset + T::COUNT
+}
+
+// The dynamic kind covers everything from RESERVED to the end of the logits
+fn kind_range<S: Segmented>(kind: S::Kind) -> Range<u32> {
+    match S::segment(kind) {
+        Some(segment) => segment.range(),
+        None if S::DYNAMIC == Some(kind) => S::RESERVED..u32::MAX,
+        None => 0..0,
+    }
+}
+
+// Ids past the end of the logits buffer are ignored
+fn clamp(range: Range<u32>, len: usize) -> Range<usize> {
+    let end = (range.end as usize).min(len);
+    (range.start as usize).min(end)..end
+}
+
+fn fill(logits: &mut [f32], range: Range<u32>, value: f32) {
+    let range = clamp(range, logits.len());
+    logits[range].fill(value);
+}
+
+fn add(logits: &mut [f32], range: Range<u32>, bias: f32) {
+    let range = clamp(range, logits.len());
+    for logit in &mut logits[range] {
+        *logit += bias;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::tests::{DynamicGingerSpace, DynamicGingerSpaceKind, GingerSpace};
+    use crate::token::tests::*;
+
+    #[test]
+    fn test_mask() {
+        // Vocabulary larger than the reserved range, with dynamic ids
+        let mut logits = vec![0.0; 1100];
+        let allowed = TokenSet::<DynamicGingerSpace>::segment::<MaoToken>()
+            .with_token(GingerToken::TextEnd)
+            .unwrap();
+        mask(&mut logits, &allowed);
+
+        let finite: Vec<usize> = (0..logits.len())
+            .filter(|&id| logits[id].is_finite())
+            .collect();
+        assert_eq!(finite, vec![1, 5, 6, 7, 8]);
+
+        let mut logits = vec![1.0; 1100];
+        mask(&mut logits, &TokenSet::<DynamicGingerSpace>::dynamic());
+        assert!(
+            logits[..1010]
+                .iter()
+                .all(|logit| *logit == f32::NEG_INFINITY)
+        );
+        assert!(logits[1010..].iter().all(|logit| *logit == 1.0));
+
+        // Buffers shorter than the reserved range are masked up to their length
+        let mut logits = vec![0.0; 8];
+        mask(&mut logits, &TokenSet::<GingerSpace>::segment::<MaoToken>());
+        assert_eq!(logits[4], f32::NEG_INFINITY);
+        assert_eq!(logits[5], 0.0);
+    }
+
+    #[test]
+    fn test_mask_closed_space() {
+        // A closed space has no dynamic tail, so ids past RESERVED stay masked
+        let mut logits = vec![0.0; 1100];
+        mask(&mut logits, &TokenSet::<GingerSpace>::all());
+        assert!(logits[..1010].iter().all(|logit| *logit == 0.0));
+        assert!(
+            logits[1010..]
+                .iter()
+                .all(|logit| *logit == f32::NEG_INFINITY)
+        );
+
+        let mut logits = vec![0.0; 1100];
+        mask(
+            &mut logits,
+            &!TokenSet::<GingerSpace>::segment::<TextTokens>(),
+        );
+        let finite: Vec<usize> = (0..logits.len())
+            .filter(|&id| logits[id].is_finite())
+            .collect();
+        assert_eq!(finite, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_segment_bias() {
+        let mut logits = vec![0.0; 1020];
+        bias_segment::<DynamicGingerSpace, MaoToken>(&mut logits, 2.0);
+        bias_token::<DynamicGingerSpace, _>(&mut logits, MaoToken::Fn, 1.0);
+        bias_kind::<DynamicGingerSpace>(&mut logits, DynamicGingerSpaceKind::Dynamic, -1.0);
+        mask_segment::<DynamicGingerSpace, SingleToken>(&mut logits);
+
+        assert_eq!(logits[4], 0.0);
+        assert_eq!(logits[5], 2.0);
+        assert_eq!(logits[7], 3.0);
+        assert_eq!(logits[9], f32::NEG_INFINITY);
+        assert_eq!(logits[1009], 0.0);
+        assert_eq!(logits[1010], -1.0);
+        assert_eq!(logits[1019], -1.0);
+
+        mask_kind::<DynamicGingerSpace>(&mut logits, DynamicGingerSpaceKind::Text);
+        assert!(
+            logits[10..1010]
+                .iter()
+                .all(|logit| *logit == f32::NEG_INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_bias_config() {
+        let bias = LogitBias::<DynamicGingerSpace>::from_names([("Mao", 2.0), ("TextEnd", -3.0)])
+            .unwrap()
+            .token(MaoToken::Struct, 0.5)
+            .kind(DynamicGingerSpaceKind::Dynamic, 1.0);
+
+        let mut logits = vec![0.0; 1012];
+        bias.apply(&mut logits);
+        assert_eq!(logits[1], -3.0);
+        assert_eq!(logits[5], 2.0);
+        assert_eq!(logits[8], 2.5);
+        assert_eq!(logits[10], 0.0);
+        assert_eq!(logits[1011], 1.0);
+
+        assert_eq!(
+            LogitBias::<DynamicGingerSpace>::new()
+                .named("Nope", 1.0)
+                .unwrap_err(),
+            UnknownNameError {
+                name: "Nope".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::marker::PhantomData;
+
+use crate::space::{Position, TokenSpace};
+use crate::token::Token;
+
+/// A fixed-length training row
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PackedRow {
+    /// Token ids, padded to the row length
+    pub ids: Vec<u32>,
+    /// 1 for real ids, 0 for padding
+    pub attention_mask: Vec<u8>,
+    /// Position of each id within its document, restarting at 0 for each
+    /// document and each row. Padding continues the last document.
+    pub position_ids: Vec<u32>,
+    /// Document boundaries in the style of flash-attention's `cu_seqlens`:
+    /// starts at 0 and ends at the number of real ids
+    pub cu_seqlens: Vec<u32>,
+}
+
+impl PackedRow {
+    /// Number of ids that are not padding
+    pub fn real_len(&self) -> usize {
+        self.cu_seqlens.last().map_or(0, |len| *len as usize)
+    }
+
+    /// Number of documents or document pieces in the row
+    pub fn documents(&self) -> usize {
+        self.cu_seqlens.len().saturating_sub(1)
+    }
+}
+
+/// Packs documents into fixed-length rows, each document followed by a
+/// separator (e.g. EOS) and each row filled up with a pad token.
+///
+/// The separator and pad are typed tokens of the space, so they cannot be
+/// confused with raw ids of another vocabulary.
+pub struct Packer<S> {
+    row_len: usize,
+    separator: u32,
+    pad: u32,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: TokenSpace> Packer<S> {
+    /// Packer for rows of `row_len` ids.
+    ///
+    /// # Panics
+    ///
+    /// If `row_len` is 0.
+    pub fn new<A: Token, B: Token>(row_len: usize, separator: A, pad: B) -> Self
+    where
+        S: Position<A> + Position<B>,
+    {
+        assert!(row_len > 0, "rows must hold at least one id");
+        Packer {
+            row_len,
+            separator: S::position_of(separator),
+            pad: S::position_of(pad),
+            _space: PhantomData,
+        }
+    }
+
+    /// Length of every row
+    pub fn row_len(&self) -> usize {
+        self.row_len
+    }
+
+    /// Pack documents in order, starting a new row when the next document
+    /// and its separator do not fit the current one. Documents longer than a
+    /// row are split across rows.
+    pub fn pack<D: AsRef<[u32]>>(&self, documents: impl IntoIterator<Item = D>) -> Vec<PackedRow> {
+        let mut rows = Rows::new(self);
+        for document in documents {
+            let document = document.as_ref();
+            if document.len() + 1 > rows.remaining() && !rows.current.ids.is_empty() {
+                rows.finish_row();
+            }
+            rows.push_document(document, self.separator);
+        }
+        rows.finish()
+    }
+
+    /// Put each document in its own row, like [`pack`](Self::pack) without
+    /// sharing rows between documents
+    pub fn pad<D: AsRef<[u32]>>(&self, documents: impl IntoIterator<Item = D>) -> Vec<PackedRow> {
+        let mut rows = Rows::new(self);
+        for document in documents {
+            rows.push_document(document.as_ref(), self.separator);
+            rows.finish_row();
+        }
+        rows.rows
+    }
+}
+
+impl<S> Clone for Packer<S> {
+    fn clone(&self) -> Self {
+        Packer {
+            row_len: self.row_len,
+            separator: self.separator,
+            pad: self.pad,
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for Packer<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Packer")
+            .field("row_len", &self.row_len)
+            .field("separator", &self.separator)
+            .field("pad", &self.pad)
+            .finish()
+    }
+}
+
+struct Rows {
+    row_len: usize,
+    pad: u32,
+    rows: Vec<PackedRow>,
+    current: PackedRow,
+    position: u32,
+}
+
+impl Rows {
+    fn new<S>(packer: &Packer<S>) -> Self {
+        Rows {
+            row_len: packer.row_len,
+            pad: packer.pad,
+            rows: Vec::new(),
+            current: PackedRow::default(),
+            position: 0,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.row_len - self.current.ids.len()
+    }
+
+    fn push_document(&mut self, document: &[u32], separator: u32) {
+        self.position = 0;
+        for &id in document.iter().chain([&separator]) {
+            if self.remaining() == 0 {
+                self.finish_row();
+            }
+            self.current.ids.push(id);
+            self.current.attention_mask.push(1);
+            self.current.position_ids.push(self.position);
+            self.position += 1;
+        }
+        self.close_document();
+    }
+
+    fn close_document(&mut self) {
+        let len = self.current.ids.len() as u32;
+        let cu_seqlens = &mut self.current.cu_seqlens;
+        if cu_seqlens.is_empty() {
+            cu_seqlens.push(0);
+        }
+        if cu_seqlens.last() != Some(&len) {
+            cu_seqlens.push(len);
+        }
+    }
+
+    fn finish_row(&mut self) {
+        if self.current.ids.is_empty() {
+            return;
+        }
+        self.close_document();
+        let mut row = std::mem::take(&mut self.current);
+        while row.ids.len() < self.row_len {
+            row.ids.push(self.pad);
+            row.attention_mask.push(0);
+            row.position_ids.push(self.position);
+            self.position += 1;
+        }
+        self.rows.push(row);
+        self.position = 0;
+    }
+
+    fn finish(mut self) -> Vec<PackedRow> {
+        self.finish_row();
+        self.rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::tests::DynamicGingerSpace;
+    use crate::token::tests::*;
+
+    fn packer(row_len: usize) -> Packer<DynamicGingerSpace> {
+        Packer::new(row_len, MaoToken::ProgramEnd, SingleToken::Single)
+    }
+
+    #[test]
+    fn test_pack() {
+        let rows = packer(6).pack([vec![1010, 1011], vec![1012], vec![1013, 1014, 1015]]);
+        assert_eq!(rows.len(), 2);
+
+        assert_eq!(rows[0].ids, vec![1010, 1011, 6, 1012, 6, 9]);
+        assert_eq!(rows[0].attention_mask, vec![1, 1, 1, 1, 1, 0]);
+        assert_eq!(rows[0].position_ids, vec![0, 1, 2, 0, 1, 2]);
+        assert_eq!(rows[0].cu_seqlens, vec![0, 3, 5]);
+        assert_eq!(rows[0].real_len(), 5);
+        assert_eq!(rows[0].documents(), 2);
+
+        assert_eq!(rows[1].ids, vec![1013, 1014, 1015, 6, 9, 9]);
+        assert_eq!(rows[1].cu_seqlens, vec![0, 4]);
+    }
+
+    #[test]
+    fn test_split_long_document() {
+        let rows = packer(3).pack([vec![1010], vec![1011, 1012, 1013, 1014]]);
+        let ids: Vec<_> = rows.iter().map(|row| row.ids.clone()).collect();
+        assert_eq!(
+            ids,
+            vec![vec![1010, 6, 9], vec![1011, 1012, 1013], vec![1014, 6, 9]]
+        );
+        assert_eq!(rows[1].position_ids, vec![0, 1, 2]);
+        assert_eq!(rows[2].position_ids, vec![0, 1, 2]);
+        assert_eq!(rows[2].cu_seqlens, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_pad() {
+        let rows = packer(4).pad([vec![1010], vec![]]);
+        assert_eq!(rows[0].ids, vec![1010, 6, 9, 9]);
+        assert_eq!(rows[1].ids, vec![6, 9, 9, 9]);
+        assert_eq!(rows[1].cu_seqlens, vec![0, 1]);
+    }
+}
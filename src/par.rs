@@ -0,0 +1,124 @@
+use rayon::prelude::*;
+
+use crate::bulk::{SegmentIndex, SegmentTable};
+use crate::error::DecodeError;
+use crate::segment::Segmented;
+use crate::space::TokenSpace;
+
+// Ids per task, large enough to amortize scheduling
+const CHUNK: usize = 1 << 14;
+
+/// Decode a slice of ids in parallel. Tokens keep the input order, and the
+/// error is the one at the lowest index, as sequential decoding would return.
+pub fn par_decode<S>(ids: &[u32]) -> Result<Vec<S>, DecodeError>
+where
+    S: Segmented + Send,
+{
+    let chunks: Vec<Result<Vec<S>, DecodeError>> = ids
+        .par_chunks(CHUNK)
+        .enumerate()
+        .map(|(chunk, ids)| {
+            let start = chunk * CHUNK;
+            ids.iter()
+                .enumerate()
+                .map(|(index, &id)| S::decode_at(start + index, id))
+                .collect()
+        })
+        .collect();
+
+    let mut tokens = Vec::with_capacity(ids.len());
+    for chunk in chunks {
+        tokens.extend(chunk?);
+    }
+    Ok(tokens)
+}
+
+/// Remainders of the dynamic ids (id - RESERVED), in input order
+pub fn par_remainders<S: TokenSpace>(ids: &[u32]) -> Vec<u32> {
+    ids.par_iter().filter_map(|id| S::remainder(*id)).collect()
+}
+
+/// Write the segment index of each id to `out` in parallel, see
+/// [`SegmentTable`].
+///
+/// # Panics
+///
+/// If the slices differ in length, or `I` cannot hold `SEGMENTS.len()`.
+pub fn par_classify_into<S, I>(ids: &[u32], out: &mut [I])
+where
+    S: Segmented,
+    I: SegmentIndex + Send,
+{
+    assert_eq!(ids.len(), out.len(), "output length must match ids");
+    let table = SegmentTable::<S>::new();
+    ids.par_chunks(CHUNK)
+        .zip(out.par_chunks_mut(CHUNK))
+        .for_each(|(ids, out)| table.classify_into(ids, out));
+}
+
+/// Number of ids in each segment, see [`SegmentTable::counts`]
+pub fn par_counts<S: Segmented>(ids: &[u32]) -> Vec<u64> {
+    let table = SegmentTable::<S>::new();
+    let segments = S::SEGMENTS.len() + 1;
+    ids.par_chunks(CHUNK).map(|ids| table.counts(ids)).reduce(
+        || vec![0; segments],
+        |mut total, counts| {
+            for (total, count) in total.iter_mut().zip(counts) {
+                *total += count;
+            }
+            total
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenIter;
+    use crate::bulk::classify_into;
+    use crate::space::tests::{DynamicGingerSpace, GingerSpace};
+
+    fn ids() -> Vec<u32> {
+        (0..100_000u32)
+            .map(|n| n.wrapping_mul(2_654_435_761) % 3000)
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_sequential() {
+        let ids = ids();
+        let expected: Vec<DynamicGingerSpace> = ids.iter().copied().try_decode_all().unwrap();
+        assert_eq!(par_decode::<DynamicGingerSpace>(&ids), Ok(expected));
+
+        let expected: Vec<u32> = ids
+            .iter()
+            .copied()
+            .remainders::<DynamicGingerSpace>()
+            .collect();
+        assert_eq!(par_remainders::<DynamicGingerSpace>(&ids), expected);
+
+        let mut sequential = vec![0u8; ids.len()];
+        let mut parallel = vec![0u8; ids.len()];
+        classify_into::<DynamicGingerSpace, _>(&ids, &mut sequential);
+        par_classify_into::<DynamicGingerSpace, _>(&ids, &mut parallel);
+        assert_eq!(parallel, sequential);
+
+        let counts = par_counts::<DynamicGingerSpace>(&ids);
+        assert_eq!(
+            counts,
+            SegmentTable::<DynamicGingerSpace>::new().counts(&ids)
+        );
+        assert_eq!(counts.iter().sum::<u64>(), ids.len() as u64);
+    }
+
+    #[test]
+    fn test_first_error() {
+        let mut ids = vec![0u32; 3 * CHUNK];
+        ids[2 * CHUNK + 5] = 1010;
+        ids[CHUNK + 7] = 2000;
+        assert!(matches!(
+            par_decode::<GingerSpace>(&ids),
+            Err(DecodeError::OutOfRange { index, value: 2000, .. }) if index == CHUNK + 7
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::error::DecodeError;
+use crate::segment::Segmented;
+use crate::space::Position;
+use crate::token::Token;
+
+/// How a token moves the position counter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Take the next position, the default
+    Advance,
+    /// Take the current position without advancing, e.g. for tokens that
+    /// annotate the following one
+    Hold,
+    /// Count on a separate counter for this rule, leaving the shared one
+    /// untouched, e.g. audio frames numbered apart from text
+    Separate,
+    /// Lay a run of consecutive tokens out row by row on a grid `width`
+    /// wide, e.g. image patches. The run shares its temporal position, and
+    /// the next token continues after the larger grid dimension.
+    Grid {
+        /// Tokens per row
+        width: u32,
+    },
+}
+
+/// Three-component position ids (temporal, height, width) for multimodal
+/// RoPE. Tokens off a grid have the same value in every component.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PositionIds {
+    /// Temporal component
+    pub temporal: Vec<u32>,
+    /// Height component
+    pub height: Vec<u32>,
+    /// Width component
+    pub width: Vec<u32>,
+}
+
+impl PositionIds {
+    /// Number of positions
+    pub fn len(&self) -> usize {
+        self.temporal.len()
+    }
+
+    /// True if there are no positions
+    pub fn is_empty(&self) -> bool {
+        self.temporal.is_empty()
+    }
+
+    fn push(&mut self, temporal: u32, height: u32, width: u32) {
+        self.temporal.push(temporal);
+        self.height.push(height);
+        self.width.push(width);
+    }
+}
+
+enum Selector<K> {
+    Ids(Range<u32>),
+    Kind(K),
+}
+
+/// Position id rules keyed by the token types of a space. The last rule
+/// matching a token decides its [`Step`]; other tokens advance.
+pub struct PositionRules<S: Segmented> {
+    rules: Vec<(Selector<S::Kind>, Step)>,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: Segmented> PositionRules<S> {
+    /// Rules advancing on every token
+    pub fn new() -> Self {
+        PositionRules {
+            rules: Vec::new(),
+            _space: PhantomData,
+        }
+    }
+
+    /// Use `step` for every token of type T
+    pub fn segment<T: Token>(mut self, step: Step) -> Self
+    where
+        S: Position<T>,
+    {
+        let offset = <S as Position<T>>::OFFSET;
+        self.rules
+            .push((Selector::Ids(offset..offset + T::COUNT), step));
+        self
+    }
+
+    /// Use `step` for every token in the segment of `kind`, including the
+    /// dynamic tail
+    pub fn kind(mut self, kind: S::Kind, step: Step) -> Self {
+        self.rules.push((Selector::Kind(kind), step));
+        self
+    }
+
+    /// Use `step` for a single token
+    pub fn token<T: Token>(mut self, token: T, step: Step) -> Self
+    where
+        S: Position<T>,
+    {
+        let id = S::position_of(token);
+        self.rules.push((Selector::Ids(id..id + 1), step));
+        self
+    }
+
+    // Index of the last rule matching `id`
+    fn rule_of(&self, id: u32) -> Option<usize> {
+        self.rules.iter().rposition(|(selector, _)| match selector {
+            Selector::Ids(range) => range.contains(&id),
+            Selector::Kind(kind) => S::kind_of(id) == Some(*kind),
+        })
+    }
+
+    /// Position ids of a sequence, starting at 0
+    pub fn positions(&self, ids: &[u32]) -> Result<PositionIds, DecodeError> {
+        let mut positions = PositionIds::default();
+        let mut next = 0u32;
+        let mut separate = vec![0u32; self.rules.len()];
+        // Rule, width, start position and length of the grid run in progress
+        let mut grid: Option<(usize, u32, u32, u32)> = None;
+
+        for (index, &id) in ids.iter().enumerate() {
+            S::decode_at(index, id)?;
+            let rule = self.rule_of(id);
+            let step = rule.map_or(Step::Advance, |rule| self.rules[rule].1);
+
+            if let Some((grid_rule, width, start, len)) = grid
+                && rule != Some(grid_rule)
+            {
+                // Continue past the larger dimension of the grid
+                next = start + len.div_ceil(width).max(width.min(len));
+                grid = None;
+            }
+
+            match (step, rule) {
+                (Step::Separate, Some(rule)) => {
+                    let counter = &mut separate[rule];
+                    positions.push(*counter, *counter, *counter);
+                    *counter += 1;
+                }
+                (Step::Grid { width }, Some(rule)) => {
+                    let (_, width, start, len) = grid.get_or_insert((rule, width.max(1), next, 0));
+                    positions.push(*start, *start + *len / *width, *start + *len % *width);
+                    *len += 1;
+                }
+                (Step::Hold, _) => positions.push(next, next, next),
+                _ => {
+                    positions.push(next, next, next);
+                    next += 1;
+                }
+            }
+        }
+        Ok(positions)
+    }
+}
+
+impl<S: Segmented> Default for PositionRules<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Segmented> fmt::Debug for PositionRules<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps: Vec<_> = self.rules.iter().map(|(_, step)| step).collect();
+        f.debug_struct("PositionRules")
+            .field("steps", &steps)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::tests::{DynamicGingerSpace, DynamicGingerSpaceKind, GingerSpace};
+    use crate::token::tests::*;
+
+    #[test]
+    fn test_advance_and_hold() {
+        let rules =
+            PositionRules::<DynamicGingerSpace>::new().token(GingerToken::AwaitAudio, Step::Hold);
+        let positions = rules.positions(&[0, 1010, 4, 1011]).unwrap();
+        assert_eq!(positions.temporal, vec![0, 1, 2, 2]);
+        assert_eq!(positions.temporal, positions.width);
+    }
+
+    #[test]
+    fn test_separate() {
+        let rules =
+            PositionRules::<DynamicGingerSpace>::new().segment::<TextTokens>(Step::Separate);
+        let positions = rules.positions(&[1010, 10, 11, 1011, 12]).unwrap();
+        assert_eq!(positions.temporal, vec![0, 0, 1, 1, 2]);
+    }
+
+    #[test]
+    fn test_grid() {
+        // Five patches two wide: three rows, so text continues 3 later
+        let rules = PositionRules::<DynamicGingerSpace>::new()
+            .segment::<TextTokens>(Step::Grid { width: 2 });
+        let positions = rules.positions(&[1010, 10, 11, 12, 13, 14, 1011]).unwrap();
+        assert_eq!(positions.temporal, vec![0, 1, 1, 1, 1, 1, 4]);
+        assert_eq!(positions.height, vec![0, 1, 1, 2, 2, 3, 4]);
+        assert_eq!(positions.width, vec![0, 1, 2, 1, 2, 1, 4]);
+    }
+
+    #[test]
+    fn test_kind_and_errors() {
+        let rules = PositionRules::<DynamicGingerSpace>::new()
+            .kind(DynamicGingerSpaceKind::Dynamic, Step::Separate);
+        let positions = rules.positions(&[5, 1010, 1011, 6]).unwrap();
+        assert_eq!(positions.temporal, vec![0, 0, 1, 1]);
+
+        let rules = PositionRules::<GingerSpace>::new();
+        assert!(matches!(
+            rules.positions(&[0, 1010]),
+            Err(DecodeError::OutOfRange { index: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+use std::marker::PhantomData;
+
+use crate::error::DecodeError;
+use crate::segment::Segmented;
+
+/// Text form of a token.
+///
+/// Implemented by `#[derive(Name)]` (the variant name), `#[range]` (a format
+/// pattern, `TypeName({})` by default) and `#[derive(Space)]` (delegating to
+/// each token type; dynamic values render through a [`DynamicVocab`]).
+pub trait Render {
+    /// Append the token's text to `out`
+    fn render(&self, out: &mut String);
+}
+
+/// Bytes of the tokens in a space's dynamic tail, e.g. a BPE vocabulary
+pub trait DynamicVocab {
+    /// Bytes of the dynamic token with this remainder (id - RESERVED)
+    fn bytes(&self, remainder: u32) -> Option<&[u8]>;
+}
+
+impl<T: AsRef<[u8]>> DynamicVocab for [T] {
+    fn bytes(&self, remainder: u32) -> Option<&[u8]> {
+        self.get(remainder as usize).map(AsRef::as_ref)
+    }
+}
+
+impl<T: AsRef<[u8]>> DynamicVocab for Vec<T> {
+    fn bytes(&self, remainder: u32) -> Option<&[u8]> {
+        self.as_slice().bytes(remainder)
+    }
+}
+
+/// Vocabulary of byte-level BPE tokens (GPT-2 style), whose strings spell raw
+/// bytes with printable stand-in characters like `Ġ` for a space
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteLevelVocab {
+    tokens: Vec<Vec<u8>>,
+}
+
+impl ByteLevelVocab {
+    /// Map each token string back to its bytes. Characters outside the
+    /// byte-level alphabet are kept as their UTF-8 encoding.
+    pub fn new<T: AsRef<str>>(tokens: impl IntoIterator<Item = T>) -> Self {
+        let tokens = tokens
+            .into_iter()
+            .map(|token| {
+                let mut bytes = Vec::new();
+                for c in token.as_ref().chars() {
+                    match byte_of_char(c) {
+                        Some(byte) => bytes.push(byte),
+                        None => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                    }
+                }
+                bytes
+            })
+            .collect();
+        ByteLevelVocab { tokens }
+    }
+}
+
+impl DynamicVocab for ByteLevelVocab {
+    fn bytes(&self, remainder: u32) -> Option<&[u8]> {
+        self.tokens.bytes(remainder)
+    }
+}
+
+// Inverse of GPT-2's bytes_to_unicode: printable bytes stand for themselves,
+// the rest were shifted to 256.. in byte order, ending below 0x144
+const BYTE_OF_CHAR: [Option<u8>; 0x144] = {
+    let mut table = [None; 0x144];
+    let mut byte = 0;
+    let mut shifted = 256;
+    while byte < 256 {
+        if matches!(byte, 0x21..=0x7e | 0xa1..=0xac | 0xae..=0xff) {
+            table[byte] = Some(byte as u8);
+        } else {
+            table[shifted] = Some(byte as u8);
+            shifted += 1;
+        }
+        byte += 1;
+    }
+    table
+};
+
+fn byte_of_char(c: char) -> Option<u8> {
+    BYTE_OF_CHAR.get(c as usize).copied().flatten()
+}
+
+/// Incremental renderer for generated ids.
+///
+/// Dynamic token bytes are buffered until they form complete UTF-8, so a
+/// character split across byte-level tokens is emitted once, whole.
+pub struct Renderer<'v, S, V: ?Sized> {
+    vocab: &'v V,
+    pending: Vec<u8>,
+    index: usize,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<'v, S, V> Renderer<'v, S, V>
+where
+    S: Segmented + Render,
+    V: DynamicVocab + ?Sized,
+{
+    /// Renderer rendering dynamic ids with `vocab`
+    pub fn new(vocab: &'v V) -> Self {
+        Renderer {
+            vocab,
+            pending: Vec::new(),
+            index: 0,
+            _space: PhantomData,
+        }
+    }
+
+    /// Render the next id, appending any completed text to `out`
+    pub fn push(&mut self, id: u32, out: &mut String) -> Result<(), DecodeError> {
+        let index = self.index;
+        self.index += 1;
+
+        if S::is_reserved(id) || S::DYNAMIC.is_none() {
+            let token = S::decode_at(index, id)?;
+            self.flush(out);
+            token.render(out);
+            return Ok(());
+        }
+
+        let bytes = self
+            .vocab
+            .bytes(id - S::RESERVED)
+            .ok_or(DecodeError::UnknownDynamic { index, value: id })?;
+        self.pending.extend_from_slice(bytes);
+        self.drain_complete(out);
+        Ok(())
+    }
+
+    /// Emit buffered bytes, replacing an incomplete trailing character with
+    /// U+FFFD, e.g. at the end of the stream
+    pub fn flush(&mut self, out: &mut String) {
+        out.push_str(&String::from_utf8_lossy(&self.pending));
+        self.pending.clear();
+    }
+
+    // Emit everything up to a trailing incomplete UTF-8 sequence. Invalid
+    // bytes can never complete, so they are replaced rather than held back.
+    fn drain_complete(&mut self, out: &mut String) {
+        let end = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            Err(_) => self.pending.len() - incomplete_suffix(&self.pending),
+        };
+        out.push_str(&String::from_utf8_lossy(&self.pending[..end]));
+        self.pending.drain(..end);
+    }
+}
+
+// Length of a trailing sequence that could still become a valid character
+fn incomplete_suffix(bytes: &[u8]) -> usize {
+    for len in 1..=bytes.len().min(3) {
+        let suffix = &bytes[bytes.len() - len..];
+        if let Err(error) = std::str::from_utf8(suffix)
+            && error.valid_up_to() == 0
+            && error.error_len().is_none()
+        {
+            return len;
+        }
+    }
+    0
+}
+
+/// Render a whole sequence to text
+pub fn render<S, V>(ids: &[u32], vocab: &V) -> Result<String, DecodeError>
+where
+    S: Segmented + Render,
+    V: DynamicVocab + ?Sized,
+{
+    let mut renderer = Renderer::<S, V>::new(vocab);
+    let mut out = String::new();
+    for &id in ids {
+        renderer.push(id, &mut out)?;
+    }
+    renderer.flush(&mut out);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultTokenSpace;
+    use crate::token::tests::GingerToken;
+
+    type Space = DefaultTokenSpace<GingerToken>;
+
+    #[test]
+    fn test_render() {
+        let vocab = vec!["hel", "lo", " world"];
+        assert_eq!(
+            render::<Space, _>(&[0, 5, 6, 7, 1], &vocab),
+            Ok("TextStarthello worldTextEnd".to_string())
+        );
+        assert_eq!(
+            render::<Space, _>(&[5, 9], &vocab),
+            Err(DecodeError::UnknownDynamic { index: 1, value: 9 })
+        );
+    }
+
+    #[test]
+    fn test_split_utf8() {
+        // "é" is 0xc3 0xa9, split across two tokens
+        let vocab: Vec<Vec<u8>> = vec![vec![b'c', b'a', b'f', 0xc3], vec![0xa9], vec![0xff]];
+        let mut renderer = Renderer::<Space, _>::new(&vocab);
+        let mut out = String::new();
+
+        renderer.push(5, &mut out).unwrap();
+        assert_eq!(out, "caf");
+        renderer.push(6, &mut out).unwrap();
+        assert_eq!(out, "café");
+
+        // Specials interrupt an incomplete character
+        renderer.push(5, &mut out).unwrap();
+        renderer.push(3, &mut out).unwrap();
+        assert_eq!(out, "cafécaf\u{fffd}AudioEnd");
+
+        // Invalid bytes are replaced without holding back later text
+        let mut out = String::new();
+        renderer.push(7, &mut out).unwrap();
+        renderer.push(5, &mut out).unwrap();
+        assert_eq!(out, "\u{fffd}caf");
+    }
+
+    #[test]
+    fn test_byte_level_vocab() {
+        let vocab = ByteLevelVocab::new(["Hello", "Ġworld", "Ċ", "Ã©"]);
+        assert_eq!(vocab.bytes(1), Some(&b" world"[..]));
+        assert_eq!(vocab.bytes(2), Some(&b"\n"[..]));
+
+        // Both ends of the shifted range, and characters outside the alphabet
+        assert_eq!(byte_of_char('\u{100}'), Some(0x00));
+        assert_eq!(byte_of_char('\u{143}'), Some(0xad));
+        assert_eq!(byte_of_char('\u{144}'), None);
+        assert_eq!(byte_of_char(' '), None);
+        assert_eq!(
+            render::<Space, _>(&[5, 6, 8, 7], &vocab),
+            Ok("Hello world\u{e9}\n".to_string())
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use crate::error::{DecodeError, TokauError};
+
+/// Integer types token IDs can be stored as, converted to and from u32 IDs
+/// without truncation. Negative values (e.g. a `-100` ignore index) and values
+/// past u32::MAX are reported as [`TokauError::InvalidId`].
+pub trait TokenIdRepr: Copy {
+    /// Item type of adapters reading IDs of this type: `T` for types whose
+    /// every value is a valid ID (u16, u32), `Result<T, DecodeError>` for the
+    /// rest, so invalid values are reported without burdening plain u32 streams
+    type Checked<T>;
+
+    /// Convert to a u32 token ID
+    fn to_id(self) -> Result<u32, TokauError>;
+
+    /// Convert a u32 token ID to this representation
+    fn from_id(id: u32) -> Result<Self, TokauError>;
+
+    /// Apply `f` to the ID, reporting an invalid value with its `index`
+    fn map_id<T>(self, index: usize, f: impl FnOnce(u32) -> T) -> Self::Checked<T>;
+
+    /// Like [`map_id`](Self::map_id), dropping IDs `f` maps to None but
+    /// keeping invalid values as errors
+    fn filter_id<T>(
+        self,
+        index: usize,
+        f: impl FnOnce(u32) -> Option<T>,
+    ) -> Option<Self::Checked<T>>;
+}
+
+// Types whose every value is a valid u32 ID
+macro_rules! impl_lossless_id_repr {
+    ($($repr:ty),*) => {
+        $(
+            impl TokenIdRepr for $repr {
+                type Checked<T> = T;
+
+                fn to_id(self) -> Result<u32, TokauError> {
+                    Ok(u32::from(self))
+                }
+
+                fn from_id(id: u32) -> Result<Self, TokauError> {
+                    <$repr>::try_from(id).map_err(|_| TokauError::InvalidId {
+                        value: id as i128,
+                    })
+                }
+
+                fn map_id<T>(self, _index: usize, f: impl FnOnce(u32) -> T) -> T {
+                    f(u32::from(self))
+                }
+
+                fn filter_id<T>(self, _index: usize, f: impl FnOnce(u32) -> Option<T>) -> Option<T> {
+                    f(u32::from(self))
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_token_id_repr {
+    ($($repr:ty),*) => {
+        $(
+            impl TokenIdRepr for $repr {
+                type Checked<T> = Result<T, DecodeError>;
+
+                fn to_id(self) -> Result<u32, TokauError> {
+                    u32::try_from(self).map_err(|_| TokauError::InvalidId {
+                        value: self as i128,
+                    })
+                }
+
+                fn from_id(id: u32) -> Result<Self, TokauError> {
+                    <$repr>::try_from(id).map_err(|_| TokauError::InvalidId {
+                        value: id as i128,
+                    })
+                }
+
+                fn map_id<T>(self, index: usize, f: impl FnOnce(u32) -> T) -> Result<T, DecodeError> {
+                    self.to_id()
+                        .map(f)
+                        .map_err(|error| DecodeError::at(index, error))
+                }
+
+                fn filter_id<T>(
+                    self,
+                    index: usize,
+                    f: impl FnOnce(u32) -> Option<T>,
+                ) -> Option<Result<T, DecodeError>> {
+                    self.map_id(index, f).transpose()
+                }
+            }
+        )*
+    };
+}
+
+impl_lossless_id_repr!(u16, u32);
+impl_token_id_repr!(u64, usize, i32, i64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_id() {
+        assert_eq!(7u16.to_id(), Ok(7));
+        assert_eq!(u32::MAX.to_id(), Ok(u32::MAX));
+        assert_eq!(1010u64.to_id(), Ok(1010));
+        assert_eq!(1010i64.to_id(), Ok(1010));
+        assert_eq!(0i32.to_id(), Ok(0));
+
+        // Negative and oversized values are errors, never truncated
+        assert_eq!(
+            (-100i64).to_id(),
+            Err(TokauError::InvalidId { value: -100 })
+        );
+        assert_eq!((-1i32).to_id(), Err(TokauError::InvalidId { value: -1 }));
+        assert_eq!(
+            (u32::MAX as u64 + 1).to_id(),
+            Err(TokauError::InvalidId {
+                value: u32::MAX as i128 + 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_id() {
+        assert_eq!(u16::from_id(65535), Ok(65535));
+        assert_eq!(
+            u16::from_id(65536),
+            Err(TokauError::InvalidId { value: 65536 })
+        );
+        assert_eq!(i64::from_id(u32::MAX), Ok(u32::MAX as i64));
+        assert_eq!(
+            i32::from_id(u32::MAX),
+            Err(TokauError::InvalidId {
+                value: u32::MAX as i128,
+            })
+        );
+        assert_eq!(u32::from_id(42), Ok(42));
+    }
+
+    #[test]
+    fn test_map_id() {
+        assert_eq!(7u16.map_id(0, |id| id + 1), 8);
+        assert_eq!(7i64.map_id(0, |id| id + 1), Ok(8));
+        assert_eq!(
+            (-100i64).map_id(3, |id| id + 1),
+            Err(DecodeError::InvalidId {
+                index: 3,
+                value: -100,
+            })
+        );
+
+        assert_eq!(7u32.filter_id(0, |_| None::<u32>), None);
+        assert_eq!(7i32.filter_id(0, |_| None::<u32>), None);
+        assert!(matches!(
+            (-1i32).filter_id(0, |_| None::<u32>),
+            Some(Err(DecodeError::InvalidId { .. }))
+        ));
+    }
+}
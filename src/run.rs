@@ -0,0 +1,196 @@
+use std::marker::PhantomData;
+
+use crate::space::OpenSpace;
+
+/// Either one reserved token or a maximal run of consecutive dynamic ids
+#[derive(Debug, Clone, PartialEq)]
+pub enum Run<S, D = Vec<u32>> {
+    /// A decoded reserved token
+    Reserved(S),
+    /// Consecutive dynamic ids. Owned runs hold remainders (id - RESERVED),
+    /// borrowed runs from [`slice_runs`] hold a [`DynamicIds`] giving both the
+    /// original ids and their remainders.
+    Dynamic(D),
+}
+
+/// Borrowed run of consecutive dynamic ids, see [`slice_runs`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DynamicIds<'a> {
+    ids: &'a [u32],
+    reserved: u32,
+}
+
+impl<'a> DynamicIds<'a> {
+    /// The original ids of the run
+    pub fn ids(&self) -> &'a [u32] {
+        self.ids
+    }
+
+    /// Remainders of the ids (id - RESERVED), as held by owned runs
+    pub fn remainders(&self) -> impl Iterator<Item = u32> + 'a {
+        let reserved = self.reserved;
+        self.ids.iter().map(move |id| id - reserved)
+    }
+
+    /// Number of ids in the run
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Check if the run has no ids
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// Iterator over the runs of an id stream, see [`TokenIter::runs`].
+///
+/// [`TokenIter::runs`]: crate::TokenIter::runs
+pub struct Runs<I, S> {
+    ids: I,
+    // Reserved id that ended the previous dynamic run
+    pending: Option<u32>,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<I, S> Runs<I, S> {
+    pub(crate) fn new(ids: I) -> Self {
+        Runs {
+            ids,
+            pending: None,
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<I: Iterator<Item = u32>, S: OpenSpace> Iterator for Runs<I, S> {
+    type Item = Run<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(id) = self.pending.take() {
+            return Some(Run::Reserved(S::decode_total(id)));
+        }
+
+        let mut run = Vec::new();
+        for id in self.ids.by_ref() {
+            match S::remainder(id) {
+                Some(remainder) => run.push(remainder),
+                None if run.is_empty() => return Some(Run::Reserved(S::decode_total(id))),
+                None => {
+                    self.pending = Some(id);
+                    break;
+                }
+            }
+        }
+        (!run.is_empty()).then_some(Run::Dynamic(run))
+    }
+}
+
+/// Split a slice of ids into runs, borrowing the dynamic runs from the input
+pub fn slice_runs<S: OpenSpace>(ids: &[u32]) -> SliceRuns<'_, S> {
+    SliceRuns {
+        ids,
+        _space: PhantomData,
+    }
+}
+
+/// Iterator over the runs of a slice, see [`slice_runs`]
+pub struct SliceRuns<'a, S> {
+    ids: &'a [u32],
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<'a, S: OpenSpace> Iterator for SliceRuns<'a, S> {
+    type Item = Run<S, DynamicIds<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&first, rest) = self.ids.split_first()?;
+        if S::is_reserved(first) {
+            self.ids = rest;
+            return Some(Run::Reserved(S::decode_total(first)));
+        }
+
+        let len = self
+            .ids
+            .iter()
+            .position(|id| S::is_reserved(*id))
+            .unwrap_or(self.ids.len());
+        let (run, rest) = self.ids.split_at(len);
+        self.ids = rest;
+        Some(Run::Dynamic(DynamicIds {
+            ids: run,
+            reserved: S::RESERVED,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenIter;
+    use crate::space::tests::DynamicGingerSpace;
+    use crate::token::tests::*;
+
+    #[test]
+    fn test_runs() {
+        let runs: Vec<_> = vec![1010, 1011, 0, 1, 1500, 10]
+            .into_iter()
+            .runs::<DynamicGingerSpace>()
+            .collect();
+
+        assert_eq!(
+            runs,
+            vec![
+                Run::Dynamic(vec![0, 1]),
+                Run::Reserved(DynamicGingerSpace::Ginger(GingerToken::TextStart)),
+                Run::Reserved(DynamicGingerSpace::Ginger(GingerToken::TextEnd)),
+                Run::Dynamic(vec![490]),
+                Run::Reserved(DynamicGingerSpace::Text(TextTokens(0))),
+            ]
+        );
+
+        assert_eq!(
+            Vec::<u32>::new()
+                .into_iter()
+                .runs::<DynamicGingerSpace>()
+                .next(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_slice_runs() {
+        let ids = [5, 2000, 2001, 2002, 6, 3000];
+        let runs: Vec<_> = slice_runs::<DynamicGingerSpace>(&ids)
+            .map(|run| match run {
+                Run::Reserved(token) => Run::Reserved(token),
+                Run::Dynamic(dynamic) => Run::Dynamic(dynamic.ids()),
+            })
+            .collect();
+
+        assert_eq!(
+            runs,
+            vec![
+                Run::Reserved(DynamicGingerSpace::Mao(MaoToken::ProgramStart)),
+                Run::Dynamic(&ids[1..4]),
+                Run::Reserved(DynamicGingerSpace::Mao(MaoToken::ProgramEnd)),
+                Run::Dynamic(&ids[5..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slice_runs_match_runs() {
+        let ids = vec![1010, 1011, 0, 1, 1500, 10];
+        let borrowed: Vec<_> = slice_runs::<DynamicGingerSpace>(&ids)
+            .map(|run| match run {
+                Run::Reserved(token) => Run::Reserved(token),
+                Run::Dynamic(dynamic) => Run::Dynamic(dynamic.remainders().collect()),
+            })
+            .collect();
+        let owned: Vec<_> = ids.into_iter().runs::<DynamicGingerSpace>().collect();
+
+        assert_eq!(borrowed, owned);
+        assert_eq!(owned[0], Run::Dynamic(vec![0, 1]));
+    }
+}
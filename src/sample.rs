@@ -0,0 +1,319 @@
+use std::ops::Range;
+
+use crate::error::TokauError;
+use crate::segment::Segmented;
+use crate::set::TokenSet;
+use crate::space::{Position, TokenSpace};
+use crate::token::Token;
+
+/// Token of type T with the highest logit, `None` if the segment is past the
+/// end of the logits or every logit in it is NaN or masked to -inf
+pub fn argmax_in<S, T>(logits: &[f32]) -> Option<T>
+where
+    S: TokenSpace + Position<T>,
+    T: Token + TryFrom<u32, Error = TokauError>,
+{
+    best(segment_ids::<S, T>(logits.len()), logits).and_then(to_token::<S, T>)
+}
+
+/// The `k` tokens of type T with the highest logits, best first. Masked
+/// tokens, with a logit of -inf, are left out.
+pub fn top_k_in<S, T>(logits: &[f32], k: usize) -> Vec<(T, f32)>
+where
+    S: TokenSpace + Position<T>,
+    T: Token + TryFrom<u32, Error = TokauError>,
+{
+    top_k(segment_ids::<S, T>(logits.len()), logits, k)
+        .into_iter()
+        .filter_map(|(id, logit)| Some((to_token::<S, T>(id)?, logit)))
+        .collect()
+}
+
+/// Space value with the highest logit among the ids in `allowed`
+pub fn argmax_set<S: Segmented>(logits: &[f32], allowed: &TokenSet<S>) -> Option<S> {
+    best(set_ids(allowed, logits.len()), logits).and_then(|id| S::try_from(id).ok())
+}
+
+/// The `k` space values in `allowed` with the highest logits, best first
+pub fn top_k_set<S: Segmented>(logits: &[f32], allowed: &TokenSet<S>, k: usize) -> Vec<(S, f32)> {
+    top_k(set_ids(allowed, logits.len()), logits, k)
+        .into_iter()
+        .filter_map(|(id, logit)| Some((S::try_from(id).ok()?, logit)))
+        .collect()
+}
+
+/// Seeded temperature sampler over a segment or token set.
+///
+/// Uses a small SplitMix64 generator so runs are reproducible from the seed
+/// without an rng dependency.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    state: u64,
+}
+
+impl Sampler {
+    /// Sampler with a fixed seed
+    pub fn new(seed: u64) -> Self {
+        Sampler { state: seed }
+    }
+
+    /// Sample a token of type T. A temperature of 0 picks the argmax. `None`
+    /// when every logit of the segment is NaN or masked to -inf.
+    pub fn sample_in<S, T>(&mut self, logits: &[f32], temperature: f32) -> Option<T>
+    where
+        S: TokenSpace + Position<T>,
+        T: Token + TryFrom<u32, Error = TokauError>,
+    {
+        self.sample(segment_ids::<S, T>(logits.len()), logits, temperature)
+            .and_then(to_token::<S, T>)
+    }
+
+    /// Sample a space value among the ids in `allowed`. A temperature of 0 picks
+    /// the argmax.
+    pub fn sample_set<S: Segmented>(
+        &mut self,
+        logits: &[f32],
+        allowed: &TokenSet<S>,
+        temperature: f32,
+    ) -> Option<S> {
+        self.sample(set_ids(allowed, logits.len()), logits, temperature)
+            .and_then(|id| S::try_from(id).ok())
+    }
+
+    fn sample(
+        &mut self,
+        ids: impl Iterator<Item = u32> + Clone,
+        logits: &[f32],
+        temperature: f32,
+    ) -> Option<u32> {
+        let argmax = best(ids.clone(), logits)?;
+        let max = logits[argmax as usize];
+        if temperature <= 0.0 || max == f32::INFINITY {
+            return Some(argmax);
+        }
+
+        // Softmax weights relative to the max logit, so exp never overflows
+        let weight = |id: u32| {
+            let logit = logits[id as usize];
+            if logit.is_nan() {
+                0.0
+            } else {
+                ((logit - max) / temperature).exp()
+            }
+        };
+        let total: f32 = ids.clone().map(weight).sum();
+        let mut target = self.next_f32() * total;
+        let mut last = None;
+        for id in ids {
+            let weight = weight(id);
+            if weight > 0.0 {
+                last = Some(id);
+                if target < weight {
+                    return Some(id);
+                }
+                target -= weight;
+            }
+        }
+        // Rounding can leave a sliver of `target` past the final weight
+        last
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // Top 24 bits give a uniform float in [0, 1)
+        (z >> 40) as f32 / (1u32 << 24) as f32
+    }
+}
+
+// Segment ids clamped to the logits buffer
+fn segment_ids<S: Position<T>, T: Token>(len: usize) -> Range<u32> {
+    let offset = <S as Position<T>>::OFFSET;
+    let end = (offset + T::COUNT).min(len.min(u32::MAX as usize) as u32);
+    offset.min(end)..end
+}
+
+// Set ids clamped to the logits buffer, including the dynamic tail if allowed
+fn set_ids<S: Segmented>(allowed: &TokenSet<S>, len: usize) -> impl Iterator<Item = u32> + Clone {
+    let len = len.min(u32::MAX as usize) as u32;
+    let dynamic = if allowed.contains_dynamic() {
+        S::RESERVED.min(len)..len
+    } else {
+        0..0
+    };
+    allowed.ids().take_while(move |id| *id < len).chain(dynamic)
+}
+
+fn to_token<S, T>(id: u32) -> Option<T>
+where
+    S: Position<T>,
+    T: Token + TryFrom<u32, Error = TokauError>,
+{
+    T::try_from(id - <S as Position<T>>::OFFSET).ok()
+}
+
+// Masked ids, with a logit of -inf, and NaN logits can never be picked
+fn selectable(logit: f32) -> bool {
+    !logit.is_nan() && logit != f32::NEG_INFINITY
+}
+
+// Lowest id wins ties
+fn best(ids: impl Iterator<Item = u32>, logits: &[f32]) -> Option<u32> {
+    let mut best: Option<(u32, f32)> = None;
+    for id in ids {
+        let logit = logits[id as usize];
+        if selectable(logit) && best.is_none_or(|(_, max)| logit > max) {
+            best = Some((id, logit));
+        }
+    }
+    best.map(|(id, _)| id)
+}
+
+fn top_k(ids: impl Iterator<Item = u32>, logits: &[f32], k: usize) -> Vec<(u32, f32)> {
+    let mut ranked: Vec<(u32, f32)> = ids
+        .map(|id| (id, logits[id as usize]))
+        .filter(|(_, logit)| selectable(*logit))
+        .collect();
+    // Stable sort keeps lower ids first among equal logits
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.truncate(k);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::tests::DynamicGingerSpace;
+    use crate::token::tests::*;
+
+    fn logits() -> Vec<f32> {
+        let mut logits = vec![0.0; 1020];
+        logits[1] = 9.0;
+        logits[6] = 3.0;
+        logits[7] = 5.0;
+        logits[8] = 5.0;
+        logits[42] = 4.0;
+        logits[1015] = 7.0;
+        logits
+    }
+
+    #[test]
+    fn test_argmax_in() {
+        let logits = logits();
+        assert_eq!(
+            argmax_in::<DynamicGingerSpace, GingerToken>(&logits),
+            Some(GingerToken::TextEnd)
+        );
+        // Ties go to the lower id
+        assert_eq!(
+            argmax_in::<DynamicGingerSpace, MaoToken>(&logits),
+            Some(MaoToken::Fn)
+        );
+        assert_eq!(
+            argmax_in::<DynamicGingerSpace, TextTokens>(&logits),
+            Some(TextTokens(32))
+        );
+        // Segment past the end of the buffer
+        assert_eq!(
+            argmax_in::<DynamicGingerSpace, TextTokens>(&logits[..5]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_top_k_in() {
+        let logits = logits();
+        assert_eq!(
+            top_k_in::<DynamicGingerSpace, MaoToken>(&logits, 3),
+            vec![
+                (MaoToken::Fn, 5.0),
+                (MaoToken::Struct, 5.0),
+                (MaoToken::ProgramEnd, 3.0)
+            ]
+        );
+        assert_eq!(
+            top_k_in::<DynamicGingerSpace, SingleToken>(&logits, 5).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_set_selection() {
+        let logits = logits();
+        let text = TokenSet::<DynamicGingerSpace>::segment::<TextTokens>();
+        assert_eq!(
+            argmax_set(&logits, &text),
+            Some(DynamicGingerSpace::Text(TextTokens(32)))
+        );
+        assert_eq!(
+            argmax_set(&logits, &text.clone().with_dynamic(true)),
+            Some(DynamicGingerSpace::Dynamic(5))
+        );
+        assert_eq!(
+            top_k_set(&logits, &TokenSet::all(), 2),
+            vec![
+                (DynamicGingerSpace::Ginger(GingerToken::TextEnd), 9.0),
+                (DynamicGingerSpace::Dynamic(5), 7.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fully_masked() {
+        let mut logits = logits();
+        logits[5..9].fill(f32::NEG_INFINITY);
+        assert_eq!(argmax_in::<DynamicGingerSpace, MaoToken>(&logits), None);
+        assert_eq!(top_k_in::<DynamicGingerSpace, MaoToken>(&logits, 2), vec![]);
+
+        let mut sampler = Sampler::new(7);
+        for temperature in [0.0, 1.0] {
+            assert_eq!(
+                sampler.sample_in::<DynamicGingerSpace, MaoToken>(&logits, temperature),
+                None
+            );
+        }
+        let mao = TokenSet::<DynamicGingerSpace>::segment::<MaoToken>();
+        assert_eq!(argmax_set(&logits, &mao), None);
+        assert_eq!(sampler.sample_set(&logits, &mao, 1.0), None);
+    }
+
+    #[test]
+    fn test_seeded_sampling() {
+        let logits = logits();
+        let mut sampler = Sampler::new(7);
+        assert_eq!(
+            sampler.sample_in::<DynamicGingerSpace, MaoToken>(&logits, 0.0),
+            Some(MaoToken::Fn)
+        );
+
+        // Same seed, same draws
+        let draw = |seed| {
+            let mut sampler = Sampler::new(seed);
+            (0..20)
+                .map(|_| sampler.sample_in::<DynamicGingerSpace, MaoToken>(&logits, 1.0))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw(3), draw(3));
+
+        // Low temperature stays on the two best tokens, masked ids are never drawn
+        let mut masked = logits.clone();
+        masked[7] = f32::NEG_INFINITY;
+        for _ in 0..100 {
+            let token = sampler.sample_in::<DynamicGingerSpace, MaoToken>(&masked, 0.1);
+            assert_eq!(token, Some(MaoToken::Struct));
+        }
+
+        let set = TokenSet::<DynamicGingerSpace>::from_ids([6, 42]);
+        for _ in 0..100 {
+            let space = sampler.sample_set(&logits, &set, 1.0).unwrap();
+            assert!(matches!(
+                space,
+                DynamicGingerSpace::Mao(MaoToken::ProgramEnd)
+                    | DynamicGingerSpace::Text(TextTokens(32))
+            ));
+        }
+    }
+}
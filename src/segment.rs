@@ -0,0 +1,355 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::Range;
+
+use crate::error::DecodeError;
+use crate::space::TokenSpace;
+
+/// One token type's contiguous block of ids within a space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment<K> {
+    /// Kind identifying this segment
+    pub kind: K,
+    /// Name of the space variant holding this segment
+    pub name: &'static str,
+    /// Global id of the first token in the segment
+    pub offset: u32,
+    /// Number of tokens in the segment
+    pub count: u32,
+    /// Names of the segment's tokens by value, empty for unnamed tokens
+    pub tokens: &'static [&'static str],
+    /// Variant names of the segment's tokens by value, ignoring `#[name]` overrides
+    pub variants: &'static [&'static str],
+    /// Start/end pairs of token values within the segment
+    pub pairs: &'static [(u32, u32)],
+}
+
+impl<K> Segment<K> {
+    /// Global id one past the last token in the segment
+    pub const fn end(&self) -> u32 {
+        self.offset + self.count
+    }
+
+    /// Global ids covered by this segment
+    pub const fn range(&self) -> Range<u32> {
+        self.offset..self.end()
+    }
+
+    /// Check if a global id falls within this segment
+    pub const fn contains(&self, id: u32) -> bool {
+        id >= self.offset && id < self.end()
+    }
+}
+
+/// Token spaces whose ids can be classified by segment without decoding.
+///
+/// Implemented by `#[derive(Space)]`, which generates a fieldless `<Space>Kind`
+/// enum with one variant per space variant (including the `#[dynamic]` one).
+pub trait Segmented: TokenSpace {
+    type Kind: Copy + Eq + Hash + Debug + 'static;
+
+    /// Static segments in layout order
+    const SEGMENTS: &'static [Segment<Self::Kind>];
+
+    /// Kind of the dynamic tail, if the space has one
+    const DYNAMIC: Option<Self::Kind>;
+
+    /// Hash of the segment names, counts and dynamic tail, for checking that
+    /// stored sequences were produced with the same layout
+    const FINGERPRINT: u64 = fingerprint(Self::SEGMENTS, Self::DYNAMIC.is_some());
+
+    /// Kind of the segment a global id belongs to, without decoding the token
+    fn kind_of(id: u32) -> Option<Self::Kind>;
+
+    /// Kind of the segment this space value belongs to
+    fn kind(&self) -> Self::Kind;
+
+    /// Segment with the given kind, `None` for the dynamic kind
+    fn segment(kind: Self::Kind) -> Option<&'static Segment<Self::Kind>> {
+        Self::SEGMENTS.iter().find(|segment| segment.kind == kind)
+    }
+
+    /// Global id of the token with this name or variant name, searching every
+    /// segment
+    fn id_of_name(name: &str) -> Option<u32> {
+        Self::SEGMENTS.iter().find_map(|segment| {
+            segment
+                .tokens
+                .iter()
+                .zip(segment.variants)
+                .position(|(token, variant)| *token == name || *variant == name)
+                .map(|value| segment.offset + value as u32)
+        })
+    }
+
+    /// Name of the token at a global id, if its type has names
+    fn name_of(id: u32) -> Option<&'static str> {
+        let segment = Self::SEGMENTS.iter().find(|segment| segment.contains(id))?;
+        segment.tokens.get((id - segment.offset) as usize).copied()
+    }
+
+    /// Global id of the token closing a span opened by `id`
+    fn closer_of(id: u32) -> Option<u32> {
+        let segment = Self::SEGMENTS.iter().find(|segment| segment.contains(id))?;
+        let value = id - segment.offset;
+        segment
+            .pairs
+            .iter()
+            .find(|(open, _)| *open == value)
+            .map(|(_, close)| segment.offset + close)
+    }
+
+    /// Global id of the token opening a span closed by `id`
+    fn opener_of(id: u32) -> Option<u32> {
+        let segment = Self::SEGMENTS.iter().find(|segment| segment.contains(id))?;
+        let value = id - segment.offset;
+        segment
+            .pairs
+            .iter()
+            .find(|(_, close)| *close == value)
+            .map(|(open, _)| segment.offset + open)
+    }
+
+    /// Segment whose space variant has this name
+    fn segment_named(name: &str) -> Option<&'static Segment<Self::Kind>> {
+        Self::SEGMENTS.iter().find(|segment| segment.name == name)
+    }
+
+    /// Kind whose space variant has this name. Only static segments are found
+    /// by default, spaces with a dynamic tail override it to include its name.
+    fn kind_named(name: &str) -> Option<Self::Kind> {
+        Self::segment_named(name).map(|segment| segment.kind)
+    }
+
+    /// Decode an ID at `index` in a sequence, reporting where it failed
+    fn decode_at(index: usize, id: u32) -> Result<Self, DecodeError> {
+        Self::try_from(id).map_err(|_| {
+            if Self::is_reserved(id) {
+                DecodeError::Gap {
+                    index,
+                    value: id,
+                    segment: Self::kind_of(id)
+                        .and_then(Self::segment)
+                        .map(|segment| segment.name),
+                }
+            } else {
+                DecodeError::OutOfRange {
+                    index,
+                    value: id,
+                    max: Self::RESERVED,
+                    nearest: Self::SEGMENTS
+                        .iter()
+                        .rev()
+                        .find(|segment| segment.count > 0)
+                        .map(|segment| segment.name),
+                }
+            }
+        })
+    }
+
+    /// Check a fingerprint recorded with a sequence against this space's layout
+    fn verify_fingerprint(found: u64) -> Result<(), DecodeError> {
+        if found == Self::FINGERPRINT {
+            Ok(())
+        } else {
+            Err(DecodeError::FingerprintMismatch {
+                expected: Self::FINGERPRINT,
+                found,
+            })
+        }
+    }
+}
+
+/// FNV-1a hash of a segment layout, usable in const context
+pub const fn fingerprint<K>(segments: &[Segment<K>], dynamic: bool) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+    let mut i = 0;
+    while i < segments.len() {
+        let name = segments[i].name.as_bytes();
+        let mut j = 0;
+        while j < name.len() {
+            hash = (hash ^ name[j] as u64).wrapping_mul(PRIME);
+            j += 1;
+        }
+        // Terminate the variable-length name before hashing the count
+        hash = (hash ^ 0xff).wrapping_mul(PRIME);
+
+        let count = segments[i].count.to_le_bytes();
+        let mut j = 0;
+        while j < count.len() {
+            hash = (hash ^ count[j] as u64).wrapping_mul(PRIME);
+            j += 1;
+        }
+        i += 1;
+    }
+
+    (hash ^ dynamic as u64).wrapping_mul(PRIME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TokauError;
+    use crate::space::Position;
+    use crate::space::tests::{
+        DynamicGingerSpace, DynamicGingerSpaceKind, GingerSpace, GingerSpaceKind,
+    };
+    use crate::token::Token;
+    use crate::token::tests::*;
+
+    // Token type whose middle value is reserved but unassigned
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    struct SparseToken(u32);
+
+    impl Token for SparseToken {
+        const COUNT: u32 = 3;
+
+        fn value(&self) -> u32 {
+            self.0
+        }
+    }
+
+    impl TryFrom<u32> for SparseToken {
+        type Error = TokauError;
+
+        fn try_from(value: u32) -> Result<Self, Self::Error> {
+            match value {
+                0 | 2 => Ok(SparseToken(value)),
+                _ => Err(TokauError::OutOfRange {
+                    value,
+                    max: Self::COUNT,
+                }),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    struct SparseSpace(SparseToken);
+
+    impl Position<SparseToken> for SparseSpace {
+        const OFFSET: u32 = 0;
+    }
+
+    impl TokenSpace for SparseSpace {
+        const RESERVED: u32 = SparseToken::COUNT;
+
+        fn value(self) -> u32 {
+            Self::position_of(self.0)
+        }
+    }
+
+    impl TryFrom<u32> for SparseSpace {
+        type Error = TokauError;
+
+        fn try_from(id: u32) -> Result<Self, Self::Error> {
+            Self::try_as::<SparseToken>(id)
+                .map(SparseSpace)
+                .ok_or(TokauError::OutOfRange {
+                    value: id,
+                    max: Self::RESERVED,
+                })
+        }
+    }
+
+    impl Segmented for SparseSpace {
+        type Kind = ();
+
+        const SEGMENTS: &'static [Segment<()>] = &[Segment {
+            kind: (),
+            name: "Sparse",
+            offset: 0,
+            count: SparseToken::COUNT,
+            tokens: SparseToken::NAMES,
+            variants: SparseToken::VARIANTS,
+            pairs: SparseToken::PAIRS,
+        }];
+
+        const DYNAMIC: Option<()> = None;
+
+        fn kind_of(id: u32) -> Option<()> {
+            Self::is_reserved(id).then_some(())
+        }
+
+        fn kind(&self) {}
+    }
+
+    #[test]
+    fn test_segment_ranges() {
+        let text = GingerSpace::SEGMENTS[3];
+        assert_eq!(text.range(), 10..1010);
+        assert_eq!(text.end(), GingerSpace::RESERVED);
+        assert!(text.contains(10));
+        assert!(text.contains(1009));
+        assert!(!text.contains(9));
+        assert!(!text.contains(1010));
+    }
+
+    #[test]
+    fn test_kind_named() {
+        assert_eq!(GingerSpace::kind_named("Mao"), Some(GingerSpaceKind::Mao));
+        assert_eq!(GingerSpace::kind_named("Dynamic"), None);
+        assert_eq!(
+            DynamicGingerSpace::kind_named("Dynamic"),
+            Some(DynamicGingerSpaceKind::Dynamic)
+        );
+        assert_eq!(DynamicGingerSpace::kind_named("Fn"), None);
+    }
+
+    #[test]
+    fn test_decode_at() {
+        assert_eq!(
+            GingerSpace::decode_at(3, 7),
+            Ok(GingerSpace::Mao(MaoToken::Fn))
+        );
+        assert_eq!(
+            GingerSpace::decode_at(3, 1010),
+            Err(DecodeError::OutOfRange {
+                index: 3,
+                value: 1010,
+                max: 1010,
+                nearest: Some("Text"),
+            })
+        );
+        assert_eq!(
+            SparseSpace::decode_at(0, 1),
+            Err(DecodeError::Gap {
+                index: 0,
+                value: 1,
+                segment: Some("Sparse"),
+            })
+        );
+        assert_eq!(
+            SparseSpace::decode_at(0, 2),
+            Ok(SparseSpace(SparseToken(2)))
+        );
+        assert_eq!(
+            DynamicGingerSpace::decode_at(9, 5000),
+            Ok(DynamicGingerSpace::Dynamic(3990))
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        // Same segments, but only one has a dynamic tail
+        assert_ne!(GingerSpace::FINGERPRINT, DynamicGingerSpace::FINGERPRINT);
+        assert_ne!(GingerSpace::FINGERPRINT, SparseSpace::FINGERPRINT);
+
+        // Stable for the same layout and usable in const context
+        const GINGER: u64 = fingerprint(GingerSpace::SEGMENTS, false);
+        assert_eq!(GINGER, GingerSpace::FINGERPRINT);
+
+        assert_eq!(
+            GingerSpace::verify_fingerprint(GingerSpace::FINGERPRINT),
+            Ok(())
+        );
+        assert_eq!(
+            GingerSpace::verify_fingerprint(DynamicGingerSpace::FINGERPRINT),
+            Err(DecodeError::FingerprintMismatch {
+                expected: GingerSpace::FINGERPRINT,
+                found: DynamicGingerSpace::FINGERPRINT,
+            })
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use std::marker::PhantomData;
+
+use crate::codec::Tokenizer;
+use crate::error::TokauError;
+use crate::segment::Segmented;
+use crate::space::{OpenSpace, Position, TokenSpace};
+use crate::token::Token;
+
+/// Builder for id sequences of a space, with a metadata value per id.
+///
+/// Tokens are pushed as typed values, so only tokens of the space compile, and
+/// dynamic ids are shifted past the reserved range for you. Each id records
+/// the metadata value current when it was pushed, e.g. a `bool` loss mask set
+/// with [`with_meta`](Self::with_meta). Use `M = ()` when no metadata is needed.
+pub struct SequenceBuilder<S, M = ()> {
+    ids: Vec<u32>,
+    meta: Vec<M>,
+    current: M,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: TokenSpace, M: Clone + Default> SequenceBuilder<S, M> {
+    /// Empty sequence, recording `M::default()` until changed
+    pub fn new() -> Self {
+        SequenceBuilder {
+            ids: Vec::new(),
+            meta: Vec::new(),
+            current: M::default(),
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<S: TokenSpace, M: Clone> SequenceBuilder<S, M> {
+    /// Push a token
+    ///
+    /// # Panics
+    ///
+    /// If the token is outside its segment, see [`try_token`](Self::try_token).
+    pub fn token<T: Token>(&mut self, token: T) -> &mut Self
+    where
+        S: Position<T>,
+    {
+        self.try_token(token)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Push a token, or fail without pushing if it is outside its segment,
+    /// e.g. a range token past its count
+    pub fn try_token<T: Token>(&mut self, token: T) -> Result<&mut Self, TokauError>
+    where
+        S: Position<T>,
+    {
+        let id = <S as Position<T>>::try_at(token)?;
+        Ok(self.push(id))
+    }
+
+    /// Push several tokens of one type
+    pub fn tokens<T: Token>(&mut self, tokens: impl IntoIterator<Item = T>) -> &mut Self
+    where
+        S: Position<T>,
+    {
+        for token in tokens {
+            self.token(token);
+        }
+        self
+    }
+
+    /// Push a dynamic id given as its remainder (id - RESERVED), e.g. a
+    /// tokenizer's output.
+    ///
+    /// # Panics
+    ///
+    /// If the shifted id does not fit in u32, see [`try_dynamic`](Self::try_dynamic).
+    pub fn dynamic(&mut self, remainder: u32) -> &mut Self
+    where
+        S: OpenSpace,
+    {
+        self.try_dynamic(remainder)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Push a dynamic id given as its remainder, or fail without pushing if
+    /// the shifted id does not fit in u32
+    pub fn try_dynamic(&mut self, remainder: u32) -> Result<&mut Self, TokauError>
+    where
+        S: OpenSpace,
+    {
+        let id = S::checked_after_reserved(remainder).ok_or(TokauError::Overflow {
+            value: remainder,
+            offset: S::RESERVED,
+        })?;
+        Ok(self.push(id))
+    }
+
+    /// Push several dynamic ids given as remainders
+    pub fn dynamic_ids(&mut self, remainders: impl IntoIterator<Item = u32>) -> &mut Self
+    where
+        S: OpenSpace,
+    {
+        for remainder in remainders {
+            self.dynamic(remainder);
+        }
+        self
+    }
+
+    /// Push the ids of `text` from `tokenizer` as dynamic ids
+    pub fn text<K: Tokenizer + ?Sized>(&mut self, text: &str, tokenizer: &K) -> &mut Self
+    where
+        S: OpenSpace,
+    {
+        self.dynamic_ids(tokenizer.encode(text))
+    }
+
+    /// Push `open`, whatever `contents` pushes, then `close`
+    ///
+    /// # Panics
+    ///
+    /// If `open` and `close` are not a pair declared with `#[pair(Open, Close)]`
+    /// or are outside their segments, see [`try_span`](Self::try_span).
+    pub fn span<A: Token, B: Token>(
+        &mut self,
+        open: A,
+        close: B,
+        contents: impl FnOnce(&mut Self),
+    ) -> &mut Self
+    where
+        S: Segmented + Position<A> + Position<B>,
+    {
+        self.try_span(open, close, contents)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Push `open`, whatever `contents` pushes, then `close`, or fail without
+    /// pushing anything if they are not a declared pair or are outside their
+    /// segments.
+    ///
+    /// Only declared pairs are accepted so the sequence can be read back with
+    /// [`TokenIter::spans`](crate::TokenIter::spans) and matched by
+    /// [`LossPolicy::span`](crate::LossPolicy::span).
+    pub fn try_span<A: Token, B: Token>(
+        &mut self,
+        open: A,
+        close: B,
+        contents: impl FnOnce(&mut Self),
+    ) -> Result<&mut Self, TokauError>
+    where
+        S: Segmented + Position<A> + Position<B>,
+    {
+        let (open, close) = checked_pair::<S, A, B>(open, close)?;
+        self.push(open);
+        contents(self);
+        Ok(self.push(close))
+    }
+
+    /// Record `meta` for the ids pushed by `contents`, then restore the
+    /// previous value
+    pub fn with_meta(&mut self, meta: M, contents: impl FnOnce(&mut Self)) -> &mut Self {
+        let previous = std::mem::replace(&mut self.current, meta);
+        contents(self);
+        self.current = previous;
+        self
+    }
+
+    /// Number of ids pushed
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// True if nothing was pushed
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The ids pushed so far
+    pub fn ids(&self) -> &[u32] {
+        &self.ids
+    }
+
+    /// Metadata of each id pushed so far
+    pub fn meta(&self) -> &[M] {
+        &self.meta
+    }
+
+    /// The finished id sequence
+    pub fn build(self) -> Vec<u32> {
+        self.ids
+    }
+
+    /// The finished id sequence and the metadata of each id
+    pub fn build_with_meta(self) -> (Vec<u32>, Vec<M>) {
+        (self.ids, self.meta)
+    }
+
+    fn push(&mut self, id: u32) -> &mut Self {
+        self.ids.push(id);
+        self.meta.push(self.current.clone());
+        self
+    }
+}
+
+impl<S: TokenSpace, M: Clone + Default> Default for SequenceBuilder<S, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, M: Clone> Clone for SequenceBuilder<S, M> {
+    fn clone(&self) -> Self {
+        SequenceBuilder {
+            ids: self.ids.clone(),
+            meta: self.meta.clone(),
+            current: self.current.clone(),
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<S, M: std::fmt::Debug> std::fmt::Debug for SequenceBuilder<S, M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SequenceBuilder")
+            .field("ids", &self.ids)
+            .field("meta", &self.meta)
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+/// Ids of `open` and `close`, checked to be a declared pair
+pub(crate) fn checked_pair<S, A, B>(open: A, close: B) -> Result<(u32, u32), TokauError>
+where
+    S: Segmented + Position<A> + Position<B>,
+    A: Token,
+    B: Token,
+{
+    let open = <S as Position<A>>::try_at(open)?;
+    let close = <S as Position<B>>::try_at(close)?;
+    if S::closer_of(open) != Some(close) {
+        return Err(TokauError::Unpaired { open, close });
+    }
+    Ok((open, close))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::tests::DynamicGingerSpace;
+    use crate::token::tests::*;
+
+    #[test]
+    fn test_build() {
+        let mut builder = SequenceBuilder::<DynamicGingerSpace>::new();
+        builder
+            .token(MaoToken::ProgramStart)
+            .span(GingerToken::TextStart, GingerToken::TextEnd, |b| {
+                b.dynamic_ids([0, 1]).tokens([TextTokens(3), TextTokens(4)]);
+            })
+            .dynamic(7);
+        assert_eq!(builder.len(), 8);
+        assert_eq!(builder.build(), vec![5, 0, 1010, 1011, 13, 14, 1, 1017]);
+    }
+
+    #[test]
+    fn test_meta() {
+        let mut builder = SequenceBuilder::<DynamicGingerSpace, bool>::new();
+        builder
+            .token(GingerToken::TextStart)
+            .with_meta(true, |b| {
+                b.dynamic(0).with_meta(false, |b| {
+                    b.token(GingerToken::AwaitAudio);
+                });
+                b.dynamic(1);
+            })
+            .token(GingerToken::TextEnd);
+
+        let (ids, loss) = builder.build_with_meta();
+        assert_eq!(ids, vec![0, 1010, 4, 1011, 1]);
+        assert_eq!(loss, vec![false, true, false, true, false]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token ID 1000 is out of valid range [0, 1000)")]
+    fn test_token_out_of_range() {
+        SequenceBuilder::<DynamicGingerSpace>::new().token(TextTokens(1000));
+    }
+
+    #[test]
+    fn test_try() {
+        let mut builder = SequenceBuilder::<DynamicGingerSpace>::new();
+        assert_eq!(
+            builder.try_token(TextTokens(1000)).map(|b| b.len()),
+            Err(TokauError::OutOfRange {
+                value: 1000,
+                max: 1000
+            })
+        );
+        assert_eq!(
+            builder.try_dynamic(u32::MAX).map(|b| b.len()),
+            Err(TokauError::Overflow {
+                value: u32::MAX,
+                offset: 1010
+            })
+        );
+        assert_eq!(
+            builder
+                .try_span(GingerToken::TextStart, GingerToken::AudioEnd, |b| {
+                    b.dynamic(0);
+                })
+                .map(|b| b.len()),
+            Err(TokauError::Unpaired { open: 0, close: 3 })
+        );
+        assert!(builder.is_empty());
+
+        builder
+            .try_span(GingerToken::TextStart, GingerToken::TextEnd, |b| {
+                b.dynamic(0);
+            })
+            .unwrap();
+        assert_eq!(builder.build(), vec![0, 1010, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token IDs 0 and 3 are not a declared start/end pair")]
+    fn test_span_not_a_pair() {
+        SequenceBuilder::<DynamicGingerSpace>::new().span(
+            GingerToken::TextStart,
+            GingerToken::AudioEnd,
+            |_| {},
+        );
+    }
+}
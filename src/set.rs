@@ -0,0 +1,519 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{BitAnd, BitOr, Not, Range, Sub};
+
+use crate::error::TokauError;
+use crate::segment::Segmented;
+use crate::space::Position;
+use crate::token::Token;
+
+/// Set of token ids in a space, e.g. the tokens allowed next during generation.
+///
+/// Reserved ids are stored as a bitset sized to `S::RESERVED`. Dynamic ids are
+/// covered by a single flag, since the dynamic tail has no fixed size. The flag
+/// is never set in a space without a dynamic tail.
+pub struct TokenSet<S> {
+    words: Cow<'static, [u64]>,
+    dynamic: bool,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: Segmented> TokenSet<S> {
+    /// Number of u64 words backing a set in this space
+    pub const WORDS: usize = S::RESERVED.div_ceil(64) as usize;
+
+    /// Bits of the last word that are inside the reserved range
+    const LAST_WORD_MASK: u64 = match S::RESERVED % 64 {
+        0 => u64::MAX,
+        bits => (1 << bits) - 1,
+    };
+
+    /// Whether the space has a dynamic tail the set can include
+    const HAS_DYNAMIC: bool = S::DYNAMIC.is_some();
+
+    /// Set containing no tokens
+    pub fn empty() -> Self {
+        TokenSet {
+            words: Cow::Owned(vec![0; Self::WORDS]),
+            dynamic: false,
+            _space: PhantomData,
+        }
+    }
+
+    /// Set containing every token in the space, including the dynamic tail if
+    /// it has one
+    pub fn all() -> Self {
+        Self::empty().complement()
+    }
+
+    /// Set containing only the dynamic tail, empty if the space has none
+    pub fn dynamic() -> Self {
+        Self::empty().with_dynamic(true)
+    }
+
+    /// Build a set from a static bitset, for fixed sets in const context.
+    /// Build the words with [`with_ids`] and [`with_range`], sized by [`Self::WORDS`].
+    ///
+    /// Panics (at compile time in const context) if `words` has the wrong length
+    /// or sets bits past RESERVED. `dynamic` is ignored if the space has no
+    /// dynamic tail.
+    pub const fn from_static(words: &'static [u64], dynamic: bool) -> Self {
+        assert!(
+            words.len() == Self::WORDS,
+            "token set words must match the space's RESERVED"
+        );
+        assert!(
+            Self::WORDS == 0 || words[Self::WORDS - 1] & !Self::LAST_WORD_MASK == 0,
+            "token set has ids past the space's RESERVED"
+        );
+        TokenSet {
+            words: Cow::Borrowed(words),
+            dynamic: dynamic && Self::HAS_DYNAMIC,
+            _space: PhantomData,
+        }
+    }
+
+    /// Set containing every token of type T
+    pub fn segment<T: Token>() -> Self
+    where
+        S: Position<T>,
+    {
+        let offset = <S as Position<T>>::OFFSET;
+        Self::from_range(offset..offset + T::COUNT)
+    }
+
+    /// Set containing the reserved ids in `range`
+    pub fn from_range(range: Range<u32>) -> Self {
+        let mut set = Self::empty();
+        set.insert_range(range);
+        set
+    }
+
+    /// Set containing the given reserved ids
+    pub fn from_ids(ids: impl IntoIterator<Item = u32>) -> Self {
+        let mut set = Self::empty();
+        for id in ids {
+            set.insert(id);
+        }
+        set
+    }
+
+    /// Set containing the given tokens of type T
+    pub fn from_tokens<T: Token>(tokens: impl IntoIterator<Item = T>) -> Self
+    where
+        S: Position<T>,
+    {
+        Self::from_ids(tokens.into_iter().map(S::position_of))
+    }
+
+    /// Include or exclude the dynamic tail, if the space has one
+    pub fn with_dynamic(mut self, dynamic: bool) -> Self {
+        self.dynamic = dynamic && Self::HAS_DYNAMIC;
+        self
+    }
+
+    /// Add a token of type T to the set, failing if it is outside its
+    /// segment, e.g. a range token past its count
+    pub fn with_token<T: Token>(mut self, token: T) -> Result<Self, TokauError>
+    where
+        S: Position<T>,
+    {
+        self.insert(<S as Position<T>>::try_at(token)?);
+        Ok(self)
+    }
+
+    /// Add a reserved id, returning false if it is past RESERVED.
+    /// Dynamic ids are only covered by the dynamic flag.
+    pub fn insert(&mut self, id: u32) -> bool {
+        if !S::is_reserved(id) {
+            return false;
+        }
+        self.words.to_mut()[(id / 64) as usize] |= 1 << (id % 64);
+        true
+    }
+
+    /// Remove a reserved id, returning false if it is past RESERVED
+    pub fn remove(&mut self, id: u32) -> bool {
+        if !S::is_reserved(id) {
+            return false;
+        }
+        self.words.to_mut()[(id / 64) as usize] &= !(1 << (id % 64));
+        true
+    }
+
+    /// Add every reserved id in `range`, ignoring the part past RESERVED
+    pub fn insert_range(&mut self, range: Range<u32>) {
+        let end = range.end.min(S::RESERVED);
+        if range.start >= end {
+            return;
+        }
+        fill_range(self.words.to_mut(), range.start..end);
+    }
+
+    /// Check if the set contains an id
+    pub fn contains(&self, id: u32) -> bool {
+        if S::is_reserved(id) {
+            self.words[(id / 64) as usize] & (1 << (id % 64)) != 0
+        } else {
+            self.dynamic
+        }
+    }
+
+    /// Check if the set contains a token of type T
+    pub fn contains_token<T: Token>(&self, token: T) -> bool
+    where
+        S: Position<T>,
+    {
+        self.contains(S::position_of(token))
+    }
+
+    /// Whether the dynamic tail is in the set
+    pub fn contains_dynamic(&self) -> bool {
+        self.dynamic
+    }
+
+    /// Number of reserved ids in the set
+    pub fn reserved_len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Check if the set contains nothing, reserved or dynamic
+    pub fn is_empty(&self) -> bool {
+        !self.dynamic && self.words.iter().all(|word| *word == 0)
+    }
+
+    /// Reserved ids in the set, in ascending order
+    pub fn ids(&self) -> impl Iterator<Item = u32> + Clone + '_ {
+        self.words.iter().enumerate().flat_map(|(index, &word)| {
+            let base = index as u32 * 64;
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| base + bit)
+        })
+    }
+
+    /// Backing bitset of reserved ids, bit `id % 64` of word `id / 64`
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Tokens in either set
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a | b, self.dynamic || other.dynamic)
+    }
+
+    /// Tokens in both sets
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a & b, self.dynamic && other.dynamic)
+    }
+
+    /// Tokens in this set but not the other
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a & !b, self.dynamic && !other.dynamic)
+    }
+
+    /// Tokens not in this set, including the dynamic tail if it was excluded and
+    /// the space has one
+    pub fn complement(&self) -> Self {
+        let mut words: Vec<u64> = self.words.iter().map(|word| !word).collect();
+        if let Some(last) = words.last_mut() {
+            *last &= Self::LAST_WORD_MASK;
+        }
+        TokenSet {
+            words: Cow::Owned(words),
+            dynamic: !self.dynamic && Self::HAS_DYNAMIC,
+            _space: PhantomData,
+        }
+    }
+
+    fn combine(&self, other: &Self, op: impl Fn(u64, u64) -> u64, dynamic: bool) -> Self {
+        TokenSet {
+            words: Cow::Owned(
+                self.words
+                    .iter()
+                    .zip(other.words.iter())
+                    .map(|(a, b)| op(*a, *b))
+                    .collect(),
+            ),
+            dynamic,
+            _space: PhantomData,
+        }
+    }
+}
+
+/// Set the bits for `ids` in a token set bitset, usable in const context
+pub const fn with_ids<const N: usize>(mut words: [u64; N], ids: &[u32]) -> [u64; N] {
+    let mut i = 0;
+    while i < ids.len() {
+        words[(ids[i] / 64) as usize] |= 1 << (ids[i] % 64);
+        i += 1;
+    }
+    words
+}
+
+/// Set the bits for every id in `range` in a token set bitset, usable in const context
+pub const fn with_range<const N: usize>(mut words: [u64; N], range: Range<u32>) -> [u64; N] {
+    fill_range(&mut words, range);
+    words
+}
+
+// Set the bits for `range` a word at a time
+const fn fill_range(words: &mut [u64], range: Range<u32>) {
+    let mut id = range.start;
+    while id < range.end {
+        let bit = id % 64;
+        let bits = if range.end - id < 64 - bit {
+            range.end - id
+        } else {
+            64 - bit
+        };
+        words[(id / 64) as usize] |= match bits {
+            64 => u64::MAX,
+            _ => ((1 << bits) - 1) << bit,
+        };
+        id += bits;
+    }
+}
+
+impl<S> Clone for TokenSet<S> {
+    fn clone(&self) -> Self {
+        TokenSet {
+            words: self.words.clone(),
+            dynamic: self.dynamic,
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<S> PartialEq for TokenSet<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.dynamic == other.dynamic && self.words == other.words
+    }
+}
+
+impl<S> Eq for TokenSet<S> {}
+
+impl<S: Segmented> fmt::Debug for TokenSet<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenSet")
+            .field("ids", &self.ids().collect::<Vec<_>>())
+            .field("dynamic", &self.dynamic)
+            .finish()
+    }
+}
+
+impl<S: Segmented> BitOr for &TokenSet<S> {
+    type Output = TokenSet<S>;
+
+    fn bitor(self, other: Self) -> TokenSet<S> {
+        self.union(other)
+    }
+}
+
+impl<S: Segmented> BitOr for TokenSet<S> {
+    type Output = TokenSet<S>;
+
+    fn bitor(self, other: Self) -> TokenSet<S> {
+        self.union(&other)
+    }
+}
+
+impl<S: Segmented> BitAnd for &TokenSet<S> {
+    type Output = TokenSet<S>;
+
+    fn bitand(self, other: Self) -> TokenSet<S> {
+        self.intersection(other)
+    }
+}
+
+impl<S: Segmented> BitAnd for TokenSet<S> {
+    type Output = TokenSet<S>;
+
+    fn bitand(self, other: Self) -> TokenSet<S> {
+        self.intersection(&other)
+    }
+}
+
+impl<S: Segmented> Sub for &TokenSet<S> {
+    type Output = TokenSet<S>;
+
+    fn sub(self, other: Self) -> TokenSet<S> {
+        self.difference(other)
+    }
+}
+
+impl<S: Segmented> Sub for TokenSet<S> {
+    type Output = TokenSet<S>;
+
+    fn sub(self, other: Self) -> TokenSet<S> {
+        self.difference(&other)
+    }
+}
+
+impl<S: Segmented> Not for &TokenSet<S> {
+    type Output = TokenSet<S>;
+
+    fn not(self) -> TokenSet<S> {
+        self.complement()
+    }
+}
+
+impl<S: Segmented> Not for TokenSet<S> {
+    type Output = TokenSet<S>;
+
+    fn not(self) -> TokenSet<S> {
+        self.complement()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::tests::{DynamicGingerSpace, GingerSpace};
+    use crate::token::tests::*;
+
+    #[test]
+    fn test_segment_sets() {
+        let mao = TokenSet::<GingerSpace>::segment::<MaoToken>();
+        assert_eq!(mao.ids().collect::<Vec<_>>(), vec![5, 6, 7, 8]);
+        assert!(mao.contains_token(MaoToken::Fn));
+        assert!(!mao.contains_token(GingerToken::TextStart));
+        assert!(!mao.contains(1010));
+
+        let text = TokenSet::<GingerSpace>::segment::<TextTokens>();
+        assert_eq!(text.reserved_len(), 1000);
+        assert!(text.contains(10));
+        assert!(text.contains(1009));
+        assert!(!text.contains(9));
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        // "Only Text tokens or Control::Stop are allowed next"
+        let allowed = TokenSet::<DynamicGingerSpace>::segment::<TextTokens>()
+            .with_token(GingerToken::TextEnd)
+            .unwrap();
+        assert!(allowed.contains(1));
+        assert!(allowed.contains(500));
+        assert!(!allowed.contains(0));
+        assert!(!allowed.contains(1010));
+        assert_eq!(
+            TokenSet::<DynamicGingerSpace>::empty().with_token(TextTokens(1000)),
+            Err(TokauError::OutOfRange {
+                value: 1000,
+                max: 1000
+            })
+        );
+
+        let ginger = TokenSet::<DynamicGingerSpace>::segment::<GingerToken>();
+        let union = &allowed | &ginger;
+        assert_eq!(union.reserved_len(), 1005);
+
+        let intersection = &allowed & &ginger;
+        assert_eq!(intersection.ids().collect::<Vec<_>>(), vec![1]);
+
+        let difference = &ginger - &allowed;
+        assert_eq!(difference.ids().collect::<Vec<_>>(), vec![0, 2, 3, 4]);
+
+        // Complement flips the dynamic tail and stays within RESERVED
+        let complement = !&allowed;
+        assert!(complement.contains(0));
+        assert!(complement.contains(1010));
+        assert!(complement.contains(u32::MAX));
+        assert!(!complement.contains(1));
+        assert_eq!(complement.reserved_len(), 1010 - 1001);
+        assert_eq!(!complement, allowed);
+
+        assert_eq!(
+            TokenSet::<DynamicGingerSpace>::all(),
+            !TokenSet::<DynamicGingerSpace>::empty()
+        );
+        assert!(TokenSet::<DynamicGingerSpace>::empty().is_empty());
+        assert!(!TokenSet::<DynamicGingerSpace>::dynamic().is_empty());
+    }
+
+    #[test]
+    fn test_closed_space_sets() {
+        // Without a dynamic tail, nothing past RESERVED is ever in the set
+        let all = TokenSet::<GingerSpace>::all();
+        assert_eq!(all.reserved_len(), 1010);
+        assert!(all.contains(1009));
+        assert!(!all.contains(1010));
+        assert!(!all.contains(5000));
+        assert!(!all.contains_dynamic());
+
+        let complement = !TokenSet::<GingerSpace>::segment::<TextTokens>();
+        assert_eq!(
+            complement.ids().collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert!(!complement.contains(5000));
+        assert_eq!(
+            !&complement,
+            TokenSet::<GingerSpace>::segment::<TextTokens>()
+        );
+        assert!((!TokenSet::<GingerSpace>::all()).is_empty());
+
+        assert!(TokenSet::<GingerSpace>::dynamic().is_empty());
+        assert!(
+            !TokenSet::<GingerSpace>::empty()
+                .with_dynamic(true)
+                .contains(1010)
+        );
+    }
+
+    #[test]
+    fn test_insert_and_ranges() {
+        let mut set = TokenSet::<GingerSpace>::empty();
+        assert!(set.insert(3));
+        assert!(!set.insert(1010));
+        assert!(set.contains(3));
+        assert!(set.remove(3));
+        assert!(!set.contains(3));
+
+        set.insert_range(1005..2000);
+        assert_eq!(
+            set.ids().collect::<Vec<_>>(),
+            vec![1005, 1006, 1007, 1008, 1009]
+        );
+
+        // Ranges spanning several words fill whole words in between
+        let set = TokenSet::<GingerSpace>::from_range(60..200);
+        assert_eq!(set.ids().collect::<Vec<_>>(), (60..200).collect::<Vec<_>>());
+        assert_eq!(set.words()[..4], [0xf << 60, u64::MAX, u64::MAX, 0xff]);
+        assert_eq!(set, TokenSet::from_ids(60..200));
+
+        let set = TokenSet::<GingerSpace>::from_ids([9, 0, 9]);
+        assert_eq!(set.ids().collect::<Vec<_>>(), vec![0, 9]);
+
+        let set = TokenSet::<GingerSpace>::from_tokens([MaoToken::Fn, MaoToken::Struct]);
+        assert_eq!(set.ids().collect::<Vec<_>>(), vec![7, 8]);
+    }
+
+    #[test]
+    fn test_const_sets() {
+        const WORDS: [u64; TokenSet::<GingerSpace>::WORDS] =
+            with_range(with_ids([0; TokenSet::<GingerSpace>::WORDS], &[0, 9]), 5..9);
+        const ALLOWED: TokenSet<GingerSpace> = TokenSet::from_static(&WORDS, false);
+
+        assert_eq!(ALLOWED.ids().collect::<Vec<_>>(), vec![0, 5, 6, 7, 8, 9]);
+        assert_eq!(
+            ALLOWED,
+            TokenSet::<GingerSpace>::segment::<MaoToken>()
+                .with_token(GingerToken::TextStart)
+                .unwrap()
+                .with_token(SingleToken::Single)
+                .unwrap()
+        );
+
+        // Static sets become owned when modified
+        let mut modified = ALLOWED.clone();
+        modified.insert(1);
+        assert!(modified.contains(1));
+        assert!(!ALLOWED.contains(1));
+    }
+}
@@ -21,6 +21,26 @@ pub trait Position<TokenType: Token> {
     fn at(token: TokenType) -> u32 {
         token.value() + Self::OFFSET
     }
+
+    /// Convert to global position, reporting values that overflow u32 or fall
+    /// past the token type's COUNT (e.g. a hand-built range token) instead of
+    /// wrapping or aliasing the next segment
+    fn try_at(token: TokenType) -> Result<u32, TokauError> {
+        let value = token.value();
+        let id = value
+            .checked_add(Self::OFFSET)
+            .ok_or(TokauError::Overflow {
+                value,
+                offset: Self::OFFSET,
+            })?;
+        if value >= TokenType::COUNT {
+            return Err(TokauError::OutOfRange {
+                value,
+                max: TokenType::COUNT,
+            });
+        }
+        Ok(id)
+    }
 }
 
 pub trait TokenSpace: Sized + TryFrom<u32, Error = TokauError> {
@@ -31,11 +51,22 @@ pub trait TokenSpace: Sized + TryFrom<u32, Error = TokauError> {
     where
         Self: Copy;
 
+    /// Convert a Space instance back to its global position value, reporting
+    /// overflow or out-of-range tokens instead of wrapping. The default defers
+    /// to `value`, so spaces holding range tokens or a dynamic tail should
+    /// override it, checking segment tokens with [`Position::try_at`].
+    fn try_value(self) -> Result<u32, TokauError>
+    where
+        Self: Copy,
+    {
+        Ok(self.value())
+    }
+
     // For NameToken tokens - try to convert global value back to token instance
-    fn try_as<T: Token>(value: u32) -> Option<T>
+    fn try_as<T>(value: u32) -> Option<T>
     where
         Self: Position<T>,
-        T: TryFrom<u32, Error = TokauError>,
+        T: Token + TryFrom<u32, Error = TokauError>,
     {
         let start = <Self as Position<T>>::OFFSET;
         value.checked_sub(start).and_then(|v| T::try_from(v).ok())
@@ -64,11 +95,31 @@ pub trait TokenSpace: Sized + TryFrom<u32, Error = TokauError> {
     fn after_reserved(value: u32) -> u32 {
         value + Self::RESERVED
     }
+
+    /// Shift a value to after the reserved range, None if the result overflows u32
+    fn checked_after_reserved(value: u32) -> Option<u32> {
+        value.checked_add(Self::RESERVED)
+    }
+}
+
+/// Marker for token spaces without a dynamic tail, where ids at or above
+/// RESERVED fail to decode
+pub trait ClosedSpace: TokenSpace {}
+
+/// Token spaces with a dynamic tail, where every id decodes
+pub trait OpenSpace: TokenSpace {
+    /// Decode any id, ids at or above RESERVED become dynamic tokens.
+    ///
+    /// Panics on a reserved id no segment claims, which only a hand-written
+    /// token whose `TryFrom` rejects some of its values can leave. Use
+    /// `TryFrom<u32>` to get that case as an error.
+    fn decode_total(id: u32) -> Self;
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
+    use crate::segment::{Segment, Segmented};
     use crate::token::Token;
     use crate::token::tests::*;
 
@@ -108,6 +159,15 @@ pub(crate) mod tests {
                 GingerSpace::Text(token) => Self::position_of(token),
             }
         }
+
+        fn try_value(self) -> Result<u32, TokauError> {
+            match self {
+                GingerSpace::Ginger(token) => <Self as Position<GingerToken>>::try_at(token),
+                GingerSpace::Mao(token) => <Self as Position<MaoToken>>::try_at(token),
+                GingerSpace::Single(token) => <Self as Position<SingleToken>>::try_at(token),
+                GingerSpace::Text(token) => <Self as Position<TextTokens>>::try_at(token),
+            }
+        }
     }
 
     impl TryFrom<u32> for GingerSpace {
@@ -172,30 +232,202 @@ pub(crate) mod tests {
                 DynamicGingerSpace::Dynamic(offset) => Self::RESERVED + offset,
             }
         }
+
+        fn try_value(self) -> Result<u32, TokauError> {
+            match self {
+                DynamicGingerSpace::Ginger(token) => <Self as Position<GingerToken>>::try_at(token),
+                DynamicGingerSpace::Mao(token) => <Self as Position<MaoToken>>::try_at(token),
+                DynamicGingerSpace::Single(token) => <Self as Position<SingleToken>>::try_at(token),
+                DynamicGingerSpace::Text(token) => <Self as Position<TextTokens>>::try_at(token),
+                DynamicGingerSpace::Dynamic(offset) => {
+                    Self::checked_after_reserved(offset).ok_or(TokauError::Overflow {
+                        value: offset,
+                        offset: Self::RESERVED,
+                    })
+                }
+            }
+        }
     }
 
     impl TryFrom<u32> for DynamicGingerSpace {
         type Error = TokauError;
 
         fn try_from(id: u32) -> Result<Self, Self::Error> {
+            // Since this has dynamic tokens, there's no real upper bound
+            Ok(Self::decode_total(id))
+        }
+    }
+
+    impl OpenSpace for DynamicGingerSpace {
+        fn decode_total(id: u32) -> Self {
             if let Some(token) = Self::try_as::<GingerToken>(id) {
-                return Ok(DynamicGingerSpace::Ginger(token));
+                return DynamicGingerSpace::Ginger(token);
             }
             if let Some(token) = Self::try_as::<MaoToken>(id) {
-                return Ok(DynamicGingerSpace::Mao(token));
+                return DynamicGingerSpace::Mao(token);
             }
             if let Some(token) = Self::try_as::<SingleToken>(id) {
-                return Ok(DynamicGingerSpace::Single(token));
+                return DynamicGingerSpace::Single(token);
             }
             if let Some(token) = Self::try_as::<TextTokens>(id) {
-                return Ok(DynamicGingerSpace::Text(token));
+                return DynamicGingerSpace::Text(token);
             }
-            if let Some(offset) = Self::remainder(id) {
-                return Ok(DynamicGingerSpace::Dynamic(offset));
+            DynamicGingerSpace::Dynamic(id.saturating_sub(Self::RESERVED))
+        }
+    }
+
+    impl ClosedSpace for GingerSpace {}
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub(crate) enum GingerSpaceKind {
+        Ginger,
+        Mao,
+        Single,
+        Text,
+    }
+
+    impl Segmented for GingerSpace {
+        type Kind = GingerSpaceKind;
+
+        const SEGMENTS: &'static [Segment<GingerSpaceKind>] = &[
+            Segment {
+                kind: GingerSpaceKind::Ginger,
+                name: "Ginger",
+                offset: 0,
+                count: GingerToken::COUNT,
+                tokens: GingerToken::NAMES,
+                variants: GingerToken::VARIANTS,
+                pairs: GingerToken::PAIRS,
+            },
+            Segment {
+                kind: GingerSpaceKind::Mao,
+                name: "Mao",
+                offset: 5,
+                count: MaoToken::COUNT,
+                tokens: MaoToken::NAMES,
+                variants: MaoToken::VARIANTS,
+                pairs: MaoToken::PAIRS,
+            },
+            Segment {
+                kind: GingerSpaceKind::Single,
+                name: "Single",
+                offset: 9,
+                count: SingleToken::COUNT,
+                tokens: SingleToken::NAMES,
+                variants: SingleToken::VARIANTS,
+                pairs: SingleToken::PAIRS,
+            },
+            Segment {
+                kind: GingerSpaceKind::Text,
+                name: "Text",
+                offset: 10,
+                count: TextTokens::COUNT,
+                tokens: TextTokens::NAMES,
+                variants: TextTokens::VARIANTS,
+                pairs: TextTokens::PAIRS,
+            },
+        ];
+
+        const DYNAMIC: Option<GingerSpaceKind> = None;
+
+        fn kind_of(id: u32) -> Option<GingerSpaceKind> {
+            match id {
+                0..=4 => Some(GingerSpaceKind::Ginger),
+                5..=8 => Some(GingerSpaceKind::Mao),
+                9 => Some(GingerSpaceKind::Single),
+                10..=1009 => Some(GingerSpaceKind::Text),
+                _ => None,
+            }
+        }
+
+        fn kind(&self) -> GingerSpaceKind {
+            match self {
+                GingerSpace::Ginger(_) => GingerSpaceKind::Ginger,
+                GingerSpace::Mao(_) => GingerSpaceKind::Mao,
+                GingerSpace::Single(_) => GingerSpaceKind::Single,
+                GingerSpace::Text(_) => GingerSpaceKind::Text,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub(crate) enum DynamicGingerSpaceKind {
+        Ginger,
+        Mao,
+        Single,
+        Text,
+        Dynamic,
+    }
+
+    impl Segmented for DynamicGingerSpace {
+        type Kind = DynamicGingerSpaceKind;
+
+        const SEGMENTS: &'static [Segment<DynamicGingerSpaceKind>] = &[
+            Segment {
+                kind: DynamicGingerSpaceKind::Ginger,
+                name: "Ginger",
+                offset: 0,
+                count: GingerToken::COUNT,
+                tokens: GingerToken::NAMES,
+                variants: GingerToken::VARIANTS,
+                pairs: GingerToken::PAIRS,
+            },
+            Segment {
+                kind: DynamicGingerSpaceKind::Mao,
+                name: "Mao",
+                offset: 5,
+                count: MaoToken::COUNT,
+                tokens: MaoToken::NAMES,
+                variants: MaoToken::VARIANTS,
+                pairs: MaoToken::PAIRS,
+            },
+            Segment {
+                kind: DynamicGingerSpaceKind::Single,
+                name: "Single",
+                offset: 9,
+                count: SingleToken::COUNT,
+                tokens: SingleToken::NAMES,
+                variants: SingleToken::VARIANTS,
+                pairs: SingleToken::PAIRS,
+            },
+            Segment {
+                kind: DynamicGingerSpaceKind::Text,
+                name: "Text",
+                offset: 10,
+                count: TextTokens::COUNT,
+                tokens: TextTokens::NAMES,
+                variants: TextTokens::VARIANTS,
+                pairs: TextTokens::PAIRS,
+            },
+        ];
+
+        const DYNAMIC: Option<DynamicGingerSpaceKind> = Some(DynamicGingerSpaceKind::Dynamic);
+
+        fn kind_named(name: &str) -> Option<DynamicGingerSpaceKind> {
+            match name {
+                "Dynamic" => Self::DYNAMIC,
+                _ => Self::segment_named(name).map(|segment| segment.kind),
+            }
+        }
+
+        fn kind_of(id: u32) -> Option<DynamicGingerSpaceKind> {
+            match id {
+                0..=4 => Some(DynamicGingerSpaceKind::Ginger),
+                5..=8 => Some(DynamicGingerSpaceKind::Mao),
+                9 => Some(DynamicGingerSpaceKind::Single),
+                10..=1009 => Some(DynamicGingerSpaceKind::Text),
+                _ => Some(DynamicGingerSpaceKind::Dynamic),
+            }
+        }
+
+        fn kind(&self) -> DynamicGingerSpaceKind {
+            match self {
+                DynamicGingerSpace::Ginger(_) => DynamicGingerSpaceKind::Ginger,
+                DynamicGingerSpace::Mao(_) => DynamicGingerSpaceKind::Mao,
+                DynamicGingerSpace::Single(_) => DynamicGingerSpaceKind::Single,
+                DynamicGingerSpace::Text(_) => DynamicGingerSpaceKind::Text,
+                DynamicGingerSpace::Dynamic(_) => DynamicGingerSpaceKind::Dynamic,
             }
-            // Since thtry_as has dynamic tokens, there's no real upper bound
-            // We never actually return an error for DynamicGingerSpace
-            unreachable!("DynamicGingerSpace accepts all values via dynamic tokens")
         }
     }
 
@@ -650,7 +882,7 @@ pub(crate) mod tests {
         let alt_maos: Vec<MaoToken> = tokens
             .clone()
             .into_iter()
-            .filter_map(|id| AlternativeSpace::try_as::<MaoToken>(id))
+            .filter_map(AlternativeSpace::try_as::<MaoToken>)
             .collect();
         // Only tokens 0,1 are present in our test vector, so we get ProgramStart, ProgramEnd
         assert_eq!(alt_maos, vec![MaoToken::ProgramStart, MaoToken::ProgramEnd]);
@@ -658,7 +890,7 @@ pub(crate) mod tests {
         // In DynamicGingerSpace, token 5 should be MaoToken::ProgramStart
         let dyn_maos: Vec<MaoToken> = tokens
             .into_iter()
-            .filter_map(|id| DynamicGingerSpace::try_as::<MaoToken>(id))
+            .filter_map(DynamicGingerSpace::try_as::<MaoToken>)
             .collect();
         assert_eq!(
             dyn_maos,
@@ -757,4 +989,108 @@ pub(crate) mod tests {
             assert_eq!(space.value(), high_value);
         }
     }
+
+    #[test]
+    fn test_decode_total() {
+        assert_eq!(
+            DynamicGingerSpace::decode_total(0),
+            DynamicGingerSpace::Ginger(GingerToken::TextStart)
+        );
+        assert_eq!(
+            DynamicGingerSpace::decode_total(7),
+            DynamicGingerSpace::Mao(MaoToken::Fn)
+        );
+        assert_eq!(
+            DynamicGingerSpace::decode_total(1009),
+            DynamicGingerSpace::Text(TextTokens(999))
+        );
+        assert_eq!(
+            DynamicGingerSpace::decode_total(1010),
+            DynamicGingerSpace::Dynamic(0)
+        );
+        assert_eq!(
+            DynamicGingerSpace::decode_total(u32::MAX),
+            DynamicGingerSpace::Dynamic(u32::MAX - 1010)
+        );
+
+        // Agrees with try_from for every id
+        for id in [0, 4, 5, 9, 10, 1009, 1010, 5000] {
+            assert_eq!(
+                DynamicGingerSpace::try_from(id),
+                Ok(DynamicGingerSpace::decode_total(id))
+            );
+        }
+    }
+
+    #[test]
+    fn test_checked_after_reserved() {
+        assert_eq!(GingerSpace::checked_after_reserved(0), Some(1010));
+        assert_eq!(
+            GingerSpace::checked_after_reserved(u32::MAX - 1010),
+            Some(u32::MAX)
+        );
+        assert_eq!(GingerSpace::checked_after_reserved(u32::MAX - 1009), None);
+        assert_eq!(GingerSpace::checked_after_reserved(u32::MAX), None);
+    }
+
+    #[test]
+    fn test_try_value() {
+        assert_eq!(GingerSpace::Text(TextTokens(999)).try_value(), Ok(1009));
+        assert_eq!(DynamicGingerSpace::Mao(MaoToken::Fn).try_value(), Ok(7));
+        assert_eq!(DynamicGingerSpace::Dynamic(0).try_value(), Ok(1010));
+        assert_eq!(
+            DynamicGingerSpace::Dynamic(u32::MAX - 1010).try_value(),
+            Ok(u32::MAX)
+        );
+        assert_eq!(
+            DynamicGingerSpace::Dynamic(u32::MAX).try_value(),
+            Err(TokauError::Overflow {
+                value: u32::MAX,
+                offset: 1010,
+            })
+        );
+
+        // Hand-built range tokens are checked like dynamic offsets
+        assert_eq!(
+            GingerSpace::Text(TextTokens(1000)).try_value(),
+            Err(TokauError::OutOfRange {
+                value: 1000,
+                max: 1000,
+            })
+        );
+        assert_eq!(
+            DynamicGingerSpace::Text(TextTokens(u32::MAX)).try_value(),
+            Err(TokauError::Overflow {
+                value: u32::MAX,
+                offset: 10,
+            })
+        );
+    }
+
+    #[test]
+    fn test_try_at() {
+        assert_eq!(
+            <GingerSpace as Position<TextTokens>>::try_at(TextTokens(0)),
+            Ok(10)
+        );
+        assert_eq!(
+            <GingerSpace as Position<TextTokens>>::try_at(TextTokens(999)),
+            Ok(1009)
+        );
+        // Hand-built range tokens past COUNT would alias the next segment or overflow
+        assert_eq!(
+            <GingerSpace as Position<TextTokens>>::try_at(TextTokens(1000)),
+            Err(TokauError::OutOfRange {
+                value: 1000,
+                max: 1000,
+            })
+        );
+        assert_eq!(
+            <GingerSpace as Position<TextTokens>>::try_at(TextTokens(u32::MAX)),
+            Err(TokauError::Overflow {
+                value: u32::MAX,
+                offset: 10,
+            })
+        );
+    }
 }
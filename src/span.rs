@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use crate::error::SpanError;
+use crate::segment::Segmented;
+
+/// Contents between a declared start/end token pair, e.g. `TextStart`/`TextEnd`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span<S> {
+    /// The opening token
+    pub open: S,
+    /// Ids between the opening and closing tokens, including nested spans
+    pub contents: Vec<u32>,
+    /// The closing token
+    pub close: S,
+    /// Index of the opening token in the sequence
+    pub start: usize,
+    /// Index of the closing token in the sequence
+    pub end: usize,
+    /// Number of spans enclosing this one
+    pub depth: usize,
+}
+
+/// Open spans of a sequence, matching closing tokens against the declared
+/// pairs. Shared by [`Spans`], [`StreamDecoder`], [`LossPolicy`] and
+/// truncation so they agree on which spans an unbalanced sequence closes.
+///
+/// [`StreamDecoder`]: crate::StreamDecoder
+/// [`LossPolicy`]: crate::LossPolicy
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SpanStack {
+    // Opening index, opening id and expected closing id of each open span
+    open: Vec<(usize, u32, u32)>,
+}
+
+/// What an id did to the open spans of a [`SpanStack`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SpanStep {
+    /// Neither opens nor closes a span
+    Other,
+    /// Opens a span
+    Open,
+    /// Closes the span opened by `open_value` at `open_index`, with `depth`
+    /// spans enclosing it. `interleaved` is set if spans opened inside it were
+    /// still open; they are discarded.
+    Close {
+        open_index: usize,
+        open_value: u32,
+        depth: usize,
+        interleaved: Option<SpanError>,
+    },
+    /// Closes no open span
+    Unopened(SpanError),
+}
+
+impl SpanStack {
+    /// Open or close spans with the id at `index`
+    pub(crate) fn push<S: Segmented>(&mut self, index: usize, id: u32) -> SpanStep {
+        if let Some(close) = S::closer_of(id) {
+            self.open.push((index, id, close));
+            return SpanStep::Open;
+        }
+        if S::opener_of(id).is_none() {
+            return SpanStep::Other;
+        }
+
+        let Some(depth) = self.open.iter().rposition(|(_, _, close)| *close == id) else {
+            return SpanStep::Unopened(SpanError::Unopened { index, value: id });
+        };
+        let interleaved = self.open[depth + 1..]
+            .last()
+            .map(|&(open_index, open_value, _)| SpanError::Interleaved {
+                index,
+                value: id,
+                open_index,
+                open_value,
+            });
+        let (open_index, open_value, _) = self.open[depth];
+        self.open.truncate(depth);
+        SpanStep::Close {
+            open_index,
+            open_value,
+            depth,
+            interleaved,
+        }
+    }
+
+    /// Number of open spans
+    pub(crate) fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// True if a span opened by `open_value` is open
+    pub(crate) fn is_open(&self, open_value: u32) -> bool {
+        self.open.iter().any(|(_, value, _)| *value == open_value)
+    }
+
+    /// Opening index of the innermost open span
+    pub(crate) fn innermost(&self) -> Option<usize> {
+        self.open.last().map(|(index, _, _)| *index)
+    }
+
+    /// Report the spans still open, outermost first, and clear them
+    pub(crate) fn finish(&mut self) -> impl Iterator<Item = SpanError> + '_ {
+        self.open
+            .drain(..)
+            .map(|(index, value, _)| SpanError::Unclosed { index, value })
+    }
+}
+
+/// Iterator over the spans of a sequence, see [`TokenIter::spans`].
+///
+/// Spans are yielded as they close, so nested spans come before the spans
+/// enclosing them. A closing token that matches an outer span while inner
+/// spans are open yields [`SpanError::Interleaved`], discards the inner spans
+/// and then yields the outer span it closed.
+///
+/// [`TokenIter::spans`]: crate::TokenIter::spans
+pub struct Spans<I, S> {
+    ids: I,
+    index: usize,
+    stack: SpanStack,
+    // Ids since the outermost open span started, at sequence index `base`
+    buffer: Vec<u32>,
+    base: usize,
+    pending: VecDeque<Result<Span<S>, SpanError>>,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<I, S> Spans<I, S> {
+    pub(crate) fn new(ids: I) -> Self {
+        Spans {
+            ids,
+            index: 0,
+            stack: SpanStack::default(),
+            buffer: Vec::new(),
+            base: 0,
+            pending: VecDeque::new(),
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<I: Iterator<Item = u32>, S: Segmented> Spans<I, S> {
+    fn span(
+        &self,
+        start: usize,
+        open: u32,
+        end: usize,
+        close: u32,
+        depth: usize,
+    ) -> Option<Span<S>> {
+        Some(Span {
+            open: S::try_from(open).ok()?,
+            contents: self.buffer[start + 1 - self.base..end - self.base].to_vec(),
+            close: S::try_from(close).ok()?,
+            start,
+            end,
+            depth,
+        })
+    }
+}
+
+impl<I: Iterator<Item = u32>, S: Segmented> Iterator for Spans<I, S> {
+    type Item = Result<Span<S>, SpanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(result) = self.pending.pop_front() {
+            return Some(result);
+        }
+
+        while let Some(id) = self.ids.next() {
+            let index = self.index;
+            self.index += 1;
+
+            // Drop buffered ids once no span is open
+            if self.stack.depth() == 0 {
+                self.buffer.clear();
+                self.base = index;
+            }
+            self.buffer.push(id);
+
+            match self.stack.push::<S>(index, id) {
+                SpanStep::Close {
+                    open_index,
+                    open_value,
+                    depth,
+                    interleaved,
+                } => {
+                    let span = self.span(open_index, open_value, index, id, depth);
+                    if let Some(error) = interleaved {
+                        self.pending.extend(span.map(Ok));
+                        return Some(Err(error));
+                    }
+                    if let Some(span) = span {
+                        return Some(Ok(span));
+                    }
+                }
+                SpanStep::Unopened(error) => return Some(Err(error)),
+                SpanStep::Open | SpanStep::Other => {}
+            }
+        }
+
+        // Spans still open at the end, outermost first
+        self.pending.extend(self.stack.finish().map(Err));
+        self.buffer.clear();
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenIter;
+    use crate::space::tests::DynamicGingerSpace;
+    use crate::token::tests::*;
+
+    #[test]
+    fn test_nested_spans() {
+        // TextStart 10 AudioStart 11 AudioEnd 12 TextEnd 5000
+        let spans: Vec<_> = vec![0, 10, 2, 11, 3, 12, 1, 5000]
+            .into_iter()
+            .spans::<DynamicGingerSpace>()
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                Ok(Span {
+                    open: DynamicGingerSpace::Ginger(GingerToken::AudioStart),
+                    contents: vec![11],
+                    close: DynamicGingerSpace::Ginger(GingerToken::AudioEnd),
+                    start: 2,
+                    end: 4,
+                    depth: 1,
+                }),
+                Ok(Span {
+                    open: DynamicGingerSpace::Ginger(GingerToken::TextStart),
+                    contents: vec![10, 2, 11, 3, 12],
+                    close: DynamicGingerSpace::Ginger(GingerToken::TextEnd),
+                    start: 0,
+                    end: 6,
+                    depth: 0,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_spans() {
+        let results: Vec<_> = vec![1, 0, 10, 1, 2, 0, 3, 2]
+            .into_iter()
+            .spans::<DynamicGingerSpace>()
+            .collect();
+
+        assert_eq!(results[0], Err(SpanError::Unopened { index: 0, value: 1 }));
+        assert_eq!(
+            results[1].as_ref().map(|span| span.contents.clone()),
+            Ok(vec![10])
+        );
+        // AudioEnd while the inner TextStart is still open
+        assert_eq!(
+            results[2],
+            Err(SpanError::Interleaved {
+                index: 6,
+                value: 3,
+                open_index: 5,
+                open_value: 0,
+            })
+        );
+        assert_eq!(
+            results[3].as_ref().map(|span| (span.start, span.end)),
+            Ok((4, 6))
+        );
+        assert_eq!(results[4], Err(SpanError::Unclosed { index: 7, value: 2 }));
+        assert_eq!(results.len(), 5);
+    }
+
+    #[test]
+    fn test_interleaved_spans() {
+        // AudioStart 20 TextStart 21 AudioEnd TextEnd
+        let results: Vec<_> = vec![2, 20, 0, 21, 3, 1]
+            .into_iter()
+            .spans::<DynamicGingerSpace>()
+            .collect();
+
+        assert_eq!(
+            results,
+            vec![
+                Err(SpanError::Interleaved {
+                    index: 4,
+                    value: 3,
+                    open_index: 2,
+                    open_value: 0,
+                }),
+                // The outer span is still closed, the inner one is discarded
+                Ok(Span {
+                    open: DynamicGingerSpace::Ginger(GingerToken::AudioStart),
+                    contents: vec![20, 0, 21],
+                    close: DynamicGingerSpace::Ginger(GingerToken::AudioEnd),
+                    start: 0,
+                    end: 4,
+                    depth: 0,
+                }),
+                Err(SpanError::Unopened { index: 5, value: 1 }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use aho_corasick::{AhoCorasick, MatchKind};
+
+use crate::error::EncodeError;
+use crate::segment::Segmented;
+
+/// How special token literals found in text are treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Specials {
+    /// Encode literals as their special token ids
+    #[default]
+    Allow,
+    /// Fail on the first literal, e.g. for untrusted user text
+    Reject,
+    /// Hand literals to the tokenizer like any other text
+    AsText,
+}
+
+/// A special token literal found in text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecialMatch {
+    /// Byte range of the literal in the text
+    pub range: Range<usize>,
+    /// Global id of the token
+    pub id: u32,
+    /// The literal, i.e. the token's `#[name]`
+    pub name: &'static str,
+}
+
+/// Encoder for text mixing special token literals (e.g. `<|im_start|>`) with
+/// ordinary text.
+///
+/// Only tokens renamed with `#[name = "..."]` are matched, so plain text that
+/// happens to contain a variant name such as `Stop` stays text. Literals are
+/// found with Aho-Corasick in one pass, preferring the longest literal at the
+/// leftmost position. Text between literals goes to a tokenizer callback whose
+/// ids are shifted past the reserved range.
+pub struct SpecialEncoder<S> {
+    matcher: AhoCorasick,
+    // Id and literal of each pattern
+    tokens: Vec<(u32, &'static str)>,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: Segmented> SpecialEncoder<S> {
+    /// Encoder matching the literals of every renamed token in the space
+    pub fn new() -> Self {
+        let mut tokens: Vec<(u32, &'static str)> = Vec::new();
+        for segment in S::SEGMENTS {
+            let named = segment.tokens.iter().zip(segment.variants).enumerate();
+            for (value, (name, variant)) in named {
+                // The first token with a literal keeps it
+                if name != variant
+                    && !name.is_empty()
+                    && !tokens.iter().any(|(_, other)| other == name)
+                {
+                    tokens.push((segment.offset + value as u32, name));
+                }
+            }
+        }
+        let matcher = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(tokens.iter().map(|(_, name)| name))
+            .expect("special token literals fit in an Aho-Corasick automaton");
+        SpecialEncoder {
+            matcher,
+            tokens,
+            _space: PhantomData,
+        }
+    }
+
+    /// Special token literals in `text`, left to right and non-overlapping
+    pub fn find<'t>(&'t self, text: &'t str) -> impl Iterator<Item = SpecialMatch> + 't {
+        self.matcher.find_iter(text).map(|found| {
+            let (id, name) = self.tokens[found.pattern().as_usize()];
+            SpecialMatch {
+                range: found.range(),
+                id,
+                name,
+            }
+        })
+    }
+
+    /// Encode `text` to ids. Literals become their token ids according to
+    /// `specials`, and the text between them is passed to `tokenize`, whose
+    /// ids are shifted with [`after_reserved`](crate::TokenSpace::after_reserved).
+    pub fn encode<F, I>(
+        &self,
+        text: &str,
+        specials: Specials,
+        mut tokenize: F,
+    ) -> Result<Vec<u32>, EncodeError>
+    where
+        F: FnMut(&str) -> I,
+        I: IntoIterator<Item = u32>,
+    {
+        let mut ids = Vec::new();
+        let mut push_text = |ids: &mut Vec<u32>, text: &str| {
+            if text.is_empty() {
+                return Ok(());
+            }
+            for id in tokenize(text) {
+                ids.push(S::checked_after_reserved(id).ok_or(EncodeError::Overflow { value: id })?);
+            }
+            Ok(())
+        };
+
+        if specials == Specials::AsText {
+            push_text(&mut ids, text)?;
+            return Ok(ids);
+        }
+
+        let mut last = 0;
+        for special in self.find(text) {
+            if specials == Specials::Reject {
+                return Err(EncodeError::DisallowedSpecial {
+                    offset: special.range.start,
+                    name: special.name,
+                });
+            }
+            push_text(&mut ids, &text[last..special.range.start])?;
+            ids.push(special.id);
+            last = special.range.end;
+        }
+        push_text(&mut ids, &text[last..])?;
+        Ok(ids)
+    }
+}
+
+impl<S: Segmented> Default for SpecialEncoder<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default::DefaultTokenSpace;
+    use crate::space::tests::DynamicGingerSpace;
+    use crate::token::tests::ChatToken;
+
+    type ChatSpace = DefaultTokenSpace<ChatToken>;
+
+    // One id per byte, so shifted ids are easy to read
+    fn bytes(text: &str) -> Vec<u32> {
+        text.bytes().map(u32::from).collect()
+    }
+
+    #[test]
+    fn test_find() {
+        let encoder = SpecialEncoder::<ChatSpace>::new();
+        let found: Vec<_> = encoder
+            .find("<s>x</s></s")
+            .map(|special| (special.range, special.id))
+            .collect();
+        assert_eq!(found, vec![(0..3, 0), (4..8, 2)]);
+
+        // Overlapping candidates resolve to the leftmost, then the longest
+        let found: Vec<_> = encoder
+            .find("<<s>user")
+            .map(|special| special.name)
+            .collect();
+        assert_eq!(found, vec!["<s>user"]);
+
+        // Tokens without a #[name] literal are never matched
+        assert_eq!(encoder.find("Pause").count(), 0);
+    }
+
+    #[test]
+    fn test_encode() {
+        let encoder = SpecialEncoder::<ChatSpace>::new();
+        assert_eq!(
+            encoder.encode("<s>hi</s>", Specials::Allow, bytes),
+            Ok(vec![0, 4 + 104, 4 + 105, 2])
+        );
+        assert_eq!(
+            encoder.encode("hi </s>", Specials::Reject, bytes),
+            Err(EncodeError::DisallowedSpecial {
+                offset: 3,
+                name: "</s>",
+            })
+        );
+        assert_eq!(
+            encoder
+                .encode("<s>", Specials::AsText, bytes)
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            encoder.encode("x", Specials::Allow, |_| vec![u32::MAX]),
+            Err(EncodeError::Overflow { value: u32::MAX })
+        );
+    }
+
+    #[test]
+    fn test_variant_names_stay_text() {
+        // Names that are just the variant name are ordinary words
+        let encoder = SpecialEncoder::<DynamicGingerSpace>::new();
+        let text = "TextStart then AudioEnd";
+        assert_eq!(
+            encoder.encode(text, Specials::Allow, bytes),
+            Ok(bytes(text).into_iter().map(|id| id + 1010).collect())
+        );
+        assert_eq!(
+            encoder
+                .encode(text, Specials::Reject, bytes)
+                .map(|ids| ids.len()),
+            Ok(23)
+        );
+    }
+}
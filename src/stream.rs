@@ -0,0 +1,409 @@
+use std::collections::VecDeque;
+
+use crate::error::{DecodeError, SpanError};
+use crate::segment::Segmented;
+use crate::span::{SpanStack, SpanStep};
+
+/// Event emitted by a [`StreamDecoder`] as ids arrive
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent<S: Segmented> {
+    /// The id at `index` starts a different segment than the previous id
+    SegmentChange {
+        /// Index of the first id in the new segment
+        index: usize,
+        /// Segment of the previous id, `None` at the start of the stream
+        from: Option<S::Kind>,
+        /// Segment of the new id
+        to: S::Kind,
+    },
+    /// A reserved token that does not open or close a span
+    Token {
+        /// Index of the id in the stream
+        index: usize,
+        /// The decoded token
+        token: S,
+    },
+    /// A token opening a span, e.g. `TextStart`
+    SpanOpen {
+        /// Index of the id in the stream
+        index: usize,
+        /// The opening token
+        token: S,
+    },
+    /// A token closing the open span it matches
+    SpanClose {
+        /// Index of the id in the stream
+        index: usize,
+        /// Index of the matching opening token
+        open_index: usize,
+        /// The closing token
+        token: S,
+    },
+    /// A run of consecutive dynamic ids, as remainders, ready to detokenize
+    DynamicRun {
+        /// Index of the first id in the run
+        start: usize,
+        /// Remainders of the ids in the run
+        remainders: Vec<u32>,
+    },
+    /// A closing token that does not match the innermost open span, or a span
+    /// left open when the stream finished. An interleaved closing token still
+    /// closes the outer span it matches, so a `SpanClose` follows.
+    Unbalanced(SpanError),
+    /// An id that does not decode in this space
+    Error(DecodeError),
+}
+
+/// Stateful decoder that turns ids into [`StreamEvent`]s one at a time, e.g.
+/// from a sampler loop.
+///
+/// Dynamic ids are buffered until the run ends or [`flush`](Self::flush) is
+/// called. Push ids with [`push`](Self::push) and take events with
+/// [`events`](Self::events), or wrap an id iterator with [`TokenIter::stream`].
+///
+/// [`TokenIter::stream`]: crate::TokenIter::stream
+pub struct StreamDecoder<S: Segmented> {
+    index: usize,
+    kind: Option<S::Kind>,
+    spans: SpanStack,
+    run_start: usize,
+    run: Vec<u32>,
+    events: VecDeque<StreamEvent<S>>,
+}
+
+impl<S: Segmented> StreamDecoder<S> {
+    /// Decoder at the start of a stream
+    pub fn new() -> Self {
+        StreamDecoder {
+            index: 0,
+            kind: None,
+            spans: SpanStack::default(),
+            run_start: 0,
+            run: Vec::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Consume the next id of the stream
+    pub fn push(&mut self, id: u32) {
+        let index = self.index;
+        self.index += 1;
+
+        let token = match S::decode_at(index, id) {
+            Ok(token) => token,
+            Err(error) => {
+                self.flush();
+                self.events.push_back(StreamEvent::Error(error));
+                return;
+            }
+        };
+
+        let kind = token.kind();
+        if Some(kind) == S::DYNAMIC {
+            self.change_segment(index, kind);
+            if self.run.is_empty() {
+                self.run_start = index;
+            }
+            self.run.push(id - S::RESERVED);
+            return;
+        }
+
+        self.flush();
+        self.change_segment(index, kind);
+
+        let event = match self.spans.push::<S>(index, id) {
+            SpanStep::Open => StreamEvent::SpanOpen { index, token },
+            SpanStep::Close {
+                open_index,
+                interleaved,
+                ..
+            } => {
+                self.events.extend(interleaved.map(StreamEvent::Unbalanced));
+                StreamEvent::SpanClose {
+                    index,
+                    open_index,
+                    token,
+                }
+            }
+            SpanStep::Unopened(error) => StreamEvent::Unbalanced(error),
+            SpanStep::Other => StreamEvent::Token { index, token },
+        };
+        self.events.push_back(event);
+    }
+
+    /// Consume several ids
+    pub fn push_all(&mut self, ids: impl IntoIterator<Item = u32>) {
+        for id in ids {
+            self.push(id);
+        }
+    }
+
+    /// Emit the buffered dynamic run now, without waiting for it to end
+    pub fn flush(&mut self) {
+        if !self.run.is_empty() {
+            self.events.push_back(StreamEvent::DynamicRun {
+                start: self.run_start,
+                remainders: std::mem::take(&mut self.run),
+            });
+            self.run_start = self.index;
+        }
+    }
+
+    /// End the stream: flush the dynamic run and report spans left open
+    pub fn finish(&mut self) {
+        self.flush();
+        self.events
+            .extend(self.spans.finish().map(StreamEvent::Unbalanced));
+    }
+
+    /// Take the events emitted so far
+    pub fn events(&mut self) -> impl Iterator<Item = StreamEvent<S>> + '_ {
+        self.events.drain(..)
+    }
+
+    /// Take the next emitted event
+    pub fn next_event(&mut self) -> Option<StreamEvent<S>> {
+        self.events.pop_front()
+    }
+
+    /// Number of ids consumed
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    /// Number of spans currently open
+    pub fn depth(&self) -> usize {
+        self.spans.depth()
+    }
+
+    fn change_segment(&mut self, index: usize, kind: S::Kind) {
+        if self.kind != Some(kind) {
+            self.events.push_back(StreamEvent::SegmentChange {
+                index,
+                from: self.kind,
+                to: kind,
+            });
+            self.kind = Some(kind);
+        }
+    }
+}
+
+impl<S: Segmented> Default for StreamDecoder<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterator over the events of an id stream, see [`TokenIter::stream`].
+///
+/// [`TokenIter::stream`]: crate::TokenIter::stream
+pub struct StreamEvents<I, S: Segmented> {
+    ids: I,
+    decoder: StreamDecoder<S>,
+    finished: bool,
+}
+
+impl<I, S: Segmented> StreamEvents<I, S> {
+    pub(crate) fn new(ids: I) -> Self {
+        StreamEvents {
+            ids,
+            decoder: StreamDecoder::new(),
+            finished: false,
+        }
+    }
+}
+
+impl<I: Iterator<Item = u32>, S: Segmented> Iterator for StreamEvents<I, S> {
+    type Item = StreamEvent<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.decoder.next_event() {
+                return Some(event);
+            }
+            if self.finished {
+                return None;
+            }
+            match self.ids.next() {
+                Some(id) => self.decoder.push(id),
+                None => {
+                    self.decoder.finish();
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenIter;
+    use crate::space::tests::{DynamicGingerSpace, DynamicGingerSpaceKind, GingerSpace};
+    use crate::token::tests::*;
+
+    #[test]
+    fn test_push_api() {
+        let mut decoder = StreamDecoder::<DynamicGingerSpace>::new();
+
+        decoder.push(0);
+        assert_eq!(
+            decoder.events().collect::<Vec<_>>(),
+            vec![
+                StreamEvent::SegmentChange {
+                    index: 0,
+                    from: None,
+                    to: DynamicGingerSpaceKind::Ginger,
+                },
+                StreamEvent::SpanOpen {
+                    index: 0,
+                    token: DynamicGingerSpace::Ginger(GingerToken::TextStart),
+                },
+            ]
+        );
+
+        // Dynamic ids are held until the run ends
+        decoder.push_all([1010, 1011]);
+        assert_eq!(
+            decoder.next_event(),
+            Some(StreamEvent::SegmentChange {
+                index: 1,
+                from: Some(DynamicGingerSpaceKind::Ginger),
+                to: DynamicGingerSpaceKind::Dynamic,
+            })
+        );
+        assert_eq!(decoder.next_event(), None);
+
+        decoder.flush();
+        decoder.push(1012);
+        decoder.push(1);
+        assert_eq!(
+            decoder.events().collect::<Vec<_>>(),
+            vec![
+                StreamEvent::DynamicRun {
+                    start: 1,
+                    remainders: vec![0, 1],
+                },
+                StreamEvent::DynamicRun {
+                    start: 3,
+                    remainders: vec![2],
+                },
+                StreamEvent::SegmentChange {
+                    index: 4,
+                    from: Some(DynamicGingerSpaceKind::Dynamic),
+                    to: DynamicGingerSpaceKind::Ginger,
+                },
+                StreamEvent::SpanClose {
+                    index: 4,
+                    open_index: 0,
+                    token: DynamicGingerSpace::Ginger(GingerToken::TextEnd),
+                },
+            ]
+        );
+        assert_eq!(decoder.position(), 5);
+        assert_eq!(decoder.depth(), 0);
+    }
+
+    #[test]
+    fn test_iterator_api() {
+        let events: Vec<_> = vec![5, 6, 2, 1020, 0, 3]
+            .into_iter()
+            .stream::<DynamicGingerSpace>()
+            .filter(|event| !matches!(event, StreamEvent::SegmentChange { .. }))
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Token {
+                    index: 0,
+                    token: DynamicGingerSpace::Mao(MaoToken::ProgramStart),
+                },
+                StreamEvent::Token {
+                    index: 1,
+                    token: DynamicGingerSpace::Mao(MaoToken::ProgramEnd),
+                },
+                StreamEvent::SpanOpen {
+                    index: 2,
+                    token: DynamicGingerSpace::Ginger(GingerToken::AudioStart),
+                },
+                StreamEvent::DynamicRun {
+                    start: 3,
+                    remainders: vec![10],
+                },
+                StreamEvent::SpanOpen {
+                    index: 4,
+                    token: DynamicGingerSpace::Ginger(GingerToken::TextStart),
+                },
+                StreamEvent::Unbalanced(SpanError::Interleaved {
+                    index: 5,
+                    value: 3,
+                    open_index: 4,
+                    open_value: 0,
+                }),
+                // The audio span still closes, the text span inside it is discarded
+                StreamEvent::SpanClose {
+                    index: 5,
+                    open_index: 2,
+                    token: DynamicGingerSpace::Ginger(GingerToken::AudioEnd),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans_agree_with_stream() {
+        // AudioStart TextStart AudioEnd TextEnd AudioStart
+        let ids = vec![2, 1020, 0, 1021, 3, 1, 2];
+
+        let from_spans: Vec<_> = ids
+            .iter()
+            .copied()
+            .spans::<DynamicGingerSpace>()
+            .map(|span| span.map(|span| (span.start, span.end)))
+            .collect();
+        let from_stream: Vec<_> = ids
+            .into_iter()
+            .stream::<DynamicGingerSpace>()
+            .filter_map(|event| match event {
+                StreamEvent::SpanClose {
+                    index, open_index, ..
+                } => Some(Ok((open_index, index))),
+                StreamEvent::Unbalanced(error) => Some(Err(error)),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            from_spans,
+            vec![
+                Err(SpanError::Interleaved {
+                    index: 4,
+                    value: 3,
+                    open_index: 2,
+                    open_value: 0,
+                }),
+                Ok((0, 4)),
+                Err(SpanError::Unopened { index: 5, value: 1 }),
+                Err(SpanError::Unclosed { index: 6, value: 2 }),
+            ]
+        );
+        assert_eq!(from_stream, from_spans);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let events: Vec<_> = vec![9, 1010].into_iter().stream::<GingerSpace>().collect();
+
+        assert_eq!(
+            events[2],
+            StreamEvent::Error(DecodeError::OutOfRange {
+                index: 1,
+                value: 1010,
+                max: 1010,
+                nearest: Some("Text"),
+            })
+        );
+        assert_eq!(events.len(), 3);
+    }
+}
@@ -0,0 +1,360 @@
+use std::marker::PhantomData;
+
+use crate::codec::Tokenizer;
+use crate::error::{EncodeError, TemplateError, TokauError};
+use crate::space::{Position, TokenSpace};
+use crate::token::Token;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Id(u32),
+    Text(&'static str),
+    // A token outside its segment, reported when the frame is encoded
+    Invalid(TokauError),
+}
+
+/// Fixed ids around message content, e.g. `<|im_start|>user\n` before it and
+/// `<|im_end|>\n` after it.
+///
+/// Special tokens are added as typed tokens, so a frame only compiles against
+/// a space that contains them. Text pieces go through the tokenizer.
+pub struct Frame<S> {
+    pieces: Vec<Piece>,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: TokenSpace> Frame<S> {
+    /// Empty frame
+    pub fn new() -> Self {
+        Frame {
+            pieces: Vec::new(),
+            _space: PhantomData,
+        }
+    }
+
+    /// Append a special token. A token outside its segment, e.g. a range
+    /// token past its count, makes encoding fail with [`EncodeError::Token`].
+    pub fn token<T: Token>(mut self, token: T) -> Self
+    where
+        S: Position<T>,
+    {
+        self.pieces.push(match <S as Position<T>>::try_at(token) {
+            Ok(id) => Piece::Id(id),
+            Err(error) => Piece::Invalid(error),
+        });
+        self
+    }
+
+    /// Append text, tokenized like message content
+    pub fn text(mut self, text: &'static str) -> Self {
+        self.pieces.push(Piece::Text(text));
+        self
+    }
+
+    fn ids<K: Tokenizer + ?Sized>(&self, tokenizer: &K) -> Result<Vec<u32>, EncodeError> {
+        let mut ids = Vec::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Id(id) => ids.push(*id),
+                Piece::Text(text) => push_text::<S, K>(&mut ids, text, tokenizer)?,
+                Piece::Invalid(error) => return Err(EncodeError::Token { error: *error }),
+            }
+        }
+        Ok(ids)
+    }
+}
+
+impl<S: TokenSpace> Default for Frame<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Clone for Frame<S> {
+    fn clone(&self) -> Self {
+        Frame {
+            pieces: self.pieces.clone(),
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for Frame<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Frame")
+            .field("pieces", &self.pieces)
+            .finish()
+    }
+}
+
+/// Roles of a chat format, usually a fieldless enum. `open` and `close` give
+/// the frame around each role's messages; an exhaustive `match` makes the
+/// compiler check that every role has one.
+pub trait ChatRole<S: TokenSpace>: Copy + Sized + 'static {
+    /// Every role, tried in order when parsing
+    const ROLES: &'static [Self];
+
+    /// Ids before the content of a message
+    fn open(self) -> Frame<S>;
+
+    /// Ids after the content of a message
+    fn close(self) -> Frame<S>;
+}
+
+/// A chat message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message<R> {
+    /// Who sent the message
+    pub role: R,
+    /// Text of the message, tokenized without special tokens
+    pub content: String,
+}
+
+impl<R> Message<R> {
+    /// Message from `role`
+    pub fn new(role: R, content: impl Into<String>) -> Self {
+        Message {
+            role,
+            content: content.into(),
+        }
+    }
+}
+
+/// Chat format turning messages into ids and back.
+///
+/// Content is always tokenized as plain text, so a message cannot inject
+/// special tokens. Parsing matches the frames of [`ChatRole::ROLES`] by id,
+/// never by comparing strings.
+pub struct ChatTemplate<S, R> {
+    begin: Frame<S>,
+    _role: PhantomData<fn() -> R>,
+}
+
+impl<S, R> ChatTemplate<S, R>
+where
+    S: TokenSpace,
+    R: ChatRole<S>,
+{
+    /// Template with nothing before the first message
+    pub fn new() -> Self {
+        ChatTemplate {
+            begin: Frame::new(),
+            _role: PhantomData,
+        }
+    }
+
+    /// Put `begin` before the first message, e.g. a BOS token
+    pub fn with_begin(mut self, begin: Frame<S>) -> Self {
+        self.begin = begin;
+        self
+    }
+
+    /// Ids of a conversation
+    pub fn encode<K>(&self, messages: &[Message<R>], tokenizer: &K) -> Result<Vec<u32>, EncodeError>
+    where
+        K: Tokenizer + ?Sized,
+    {
+        let mut ids = self.begin.ids(tokenizer)?;
+        for message in messages {
+            ids.extend(message.role.open().ids(tokenizer)?);
+            push_text::<S, K>(&mut ids, &message.content, tokenizer)?;
+            ids.extend(message.role.close().ids(tokenizer)?);
+        }
+        Ok(ids)
+    }
+
+    /// Ids of a conversation followed by the opening of a `reply` message,
+    /// for the model to complete
+    pub fn encode_prompt<K>(
+        &self,
+        messages: &[Message<R>],
+        reply: R,
+        tokenizer: &K,
+    ) -> Result<Vec<u32>, EncodeError>
+    where
+        K: Tokenizer + ?Sized,
+    {
+        let mut ids = self.encode(messages, tokenizer)?;
+        ids.extend(reply.open().ids(tokenizer)?);
+        Ok(ids)
+    }
+
+    /// Messages of a conversation encoded with this template. Content is
+    /// decoded with `tokenizer`, replacing invalid UTF-8 with U+FFFD.
+    pub fn parse<K>(&self, ids: &[u32], tokenizer: &K) -> Result<Vec<Message<R>>, TemplateError>
+    where
+        K: Tokenizer + ?Sized,
+    {
+        // Frames whose text does not tokenize cannot appear in `ids` anyway
+        let frame = |frame: Frame<S>| frame.ids(tokenizer).ok();
+        let roles: Vec<_> = R::ROLES
+            .iter()
+            .filter_map(|role| Some((*role, frame(role.open())?, frame(role.close())?)))
+            .collect();
+
+        let mut position = match frame(self.begin.clone()) {
+            Some(begin) if ids.starts_with(&begin) => begin.len(),
+            _ => return Err(unexpected(ids, 0)),
+        };
+
+        let mut messages = Vec::new();
+        while position < ids.len() {
+            let start = position;
+            // Longest opening frame wins, earlier roles break ties
+            let (role, open, close) = roles
+                .iter()
+                .filter(|(_, open, _)| ids[start..].starts_with(open))
+                .rev()
+                .max_by_key(|(_, open, _)| open.len())
+                .ok_or_else(|| unexpected(ids, start))?;
+
+            position += open.len();
+            let content_start = position;
+            loop {
+                if !close.is_empty() && ids[position..].starts_with(close) {
+                    break;
+                }
+                match ids.get(position) {
+                    Some(&id) if !S::is_reserved(id) => position += 1,
+                    Some(_) if close.is_empty() => break,
+                    Some(_) => return Err(unexpected(ids, position)),
+                    None if close.is_empty() => break,
+                    None => return Err(TemplateError::Unterminated { index: start }),
+                }
+            }
+            // A role with empty frames matches a reserved id without consuming it
+            if position == start && close.is_empty() {
+                return Err(unexpected(ids, start));
+            }
+
+            let remainders: Vec<u32> = ids[content_start..position]
+                .iter()
+                .map(|id| id - S::RESERVED)
+                .collect();
+            let bytes = tokenizer.decode(&remainders);
+            messages.push(Message {
+                role: *role,
+                content: String::from_utf8(bytes)
+                    .unwrap_or_else(|error| String::from_utf8_lossy(error.as_bytes()).into_owned()),
+            });
+            position += close.len();
+        }
+        Ok(messages)
+    }
+}
+
+impl<S, R> Default for ChatTemplate<S, R>
+where
+    S: TokenSpace,
+    R: ChatRole<S>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn push_text<S, K>(ids: &mut Vec<u32>, text: &str, tokenizer: &K) -> Result<(), EncodeError>
+where
+    S: TokenSpace,
+    K: Tokenizer + ?Sized,
+{
+    if text.is_empty() {
+        return Ok(());
+    }
+    for id in tokenizer.encode(text) {
+        ids.push(S::checked_after_reserved(id).ok_or(EncodeError::Overflow { value: id })?);
+    }
+    Ok(())
+}
+
+fn unexpected(ids: &[u32], index: usize) -> TemplateError {
+    match ids.get(index) {
+        Some(&value) => TemplateError::Unexpected { index, value },
+        None => TemplateError::Unterminated { index },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ByteTokenizer;
+    use crate::space::tests::DynamicGingerSpace;
+    use crate::token::tests::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Role {
+        Text,
+        Audio,
+    }
+
+    impl ChatRole<DynamicGingerSpace> for Role {
+        const ROLES: &'static [Self] = &[Role::Text, Role::Audio];
+
+        fn open(self) -> Frame<DynamicGingerSpace> {
+            match self {
+                Role::Text => Frame::new().token(GingerToken::TextStart),
+                Role::Audio => Frame::new().token(GingerToken::AudioStart).text(">"),
+            }
+        }
+
+        fn close(self) -> Frame<DynamicGingerSpace> {
+            match self {
+                Role::Text => Frame::new().token(GingerToken::TextEnd),
+                Role::Audio => Frame::new().token(GingerToken::AudioEnd),
+            }
+        }
+    }
+
+    fn template() -> ChatTemplate<DynamicGingerSpace, Role> {
+        ChatTemplate::new().with_begin(Frame::new().token(MaoToken::ProgramStart))
+    }
+
+    #[test]
+    fn test_encode() {
+        let messages = [
+            Message::new(Role::Text, "hi"),
+            Message::new(Role::Audio, ""),
+        ];
+        assert_eq!(
+            template().encode(&messages, &ByteTokenizer),
+            Ok(vec![5, 0, 1010 + 104, 1010 + 105, 1, 2, 1010 + 62, 3])
+        );
+        assert_eq!(
+            template().encode_prompt(&messages[..1], Role::Audio, &ByteTokenizer),
+            Ok(vec![5, 0, 1010 + 104, 1010 + 105, 1, 2, 1010 + 62])
+        );
+
+        // A range token past its count has no id to encode
+        let template = template().with_begin(Frame::new().token(TextTokens(1000)));
+        assert_eq!(
+            template.encode(&messages, &ByteTokenizer),
+            Err(EncodeError::Token {
+                error: TokauError::OutOfRange {
+                    value: 1000,
+                    max: 1000
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let messages = vec![Message::new(Role::Audio, "a"), Message::new(Role::Text, "")];
+        let ids = template().encode(&messages, &ByteTokenizer).unwrap();
+        assert_eq!(template().parse(&ids, &ByteTokenizer), Ok(messages));
+
+        // Missing begin, stray reserved id in content, missing close
+        assert_eq!(
+            template().parse(&[0, 1], &ByteTokenizer),
+            Err(TemplateError::Unexpected { index: 0, value: 0 })
+        );
+        assert_eq!(
+            template().parse(&[5, 0, 1010, 9, 1], &ByteTokenizer),
+            Err(TemplateError::Unexpected { index: 3, value: 9 })
+        );
+        assert_eq!(
+            template().parse(&[5, 0, 1010], &ByteTokenizer),
+            Err(TemplateError::Unterminated { index: 1 })
+        );
+    }
+}
@@ -1,7 +1,23 @@
 // Base trait for anything that can be positioned in a token space at compile time.
 pub trait Token {
     const COUNT: u32;
+
+    /// Names of the tokens by value, empty for unnamed (range) tokens
+    const NAMES: &'static [&'static str] = &[];
+
+    /// Variant names by value, the same as NAMES unless a token is renamed
+    /// with `#[name = "..."]`
+    const VARIANTS: &'static [&'static str] = Self::NAMES;
+
+    /// Start/end pairs of token values, e.g. `TextStart`/`TextEnd`
+    const PAIRS: &'static [(u32, u32)] = &[];
+
     fn value(&self) -> u32;
+
+    /// Name of this token, if the type has names
+    fn name(&self) -> Option<&'static str> {
+        Self::NAMES.get(self.value() as usize).copied()
+    }
 }
 
 #[cfg(test)]
@@ -83,12 +99,26 @@ pub mod tests {
 
     impl Token for GingerToken {
         const COUNT: u32 = 5;
+        const NAMES: &'static [&'static str] = &[
+            "TextStart",
+            "TextEnd",
+            "AudioStart",
+            "AudioEnd",
+            "AwaitAudio",
+        ];
+        const PAIRS: &'static [(u32, u32)] = &[(0, 1), (2, 3)];
 
         fn value(&self) -> u32 {
             *self as u32
         }
     }
 
+    impl crate::render::Render for GingerToken {
+        fn render(&self, out: &mut String) {
+            out.push_str(self.name().unwrap_or_default());
+        }
+    }
+
     impl TryFrom<u32> for GingerToken {
         type Error = TokauError;
 
@@ -107,6 +137,49 @@ pub mod tests {
         }
     }
 
+    // Tokens with literals, except Pause which keeps its variant name
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    #[repr(u32)]
+    pub enum ChatToken {
+        Bos = 0,
+        BosUser = 1,
+        Eos = 2,
+        Pause = 3,
+    }
+
+    impl Token for ChatToken {
+        const COUNT: u32 = 4;
+        const NAMES: &'static [&'static str] = &["<s>", "<s>user", "</s>", "Pause"];
+        const VARIANTS: &'static [&'static str] = &["Bos", "BosUser", "Eos", "Pause"];
+
+        fn value(&self) -> u32 {
+            *self as u32
+        }
+    }
+
+    impl crate::render::Render for ChatToken {
+        fn render(&self, out: &mut String) {
+            out.push_str(self.name().unwrap_or_default());
+        }
+    }
+
+    impl TryFrom<u32> for ChatToken {
+        type Error = TokauError;
+
+        fn try_from(value: u32) -> Result<Self, Self::Error> {
+            match value {
+                0 => Ok(ChatToken::Bos),
+                1 => Ok(ChatToken::BosUser),
+                2 => Ok(ChatToken::Eos),
+                3 => Ok(ChatToken::Pause),
+                _ => Err(TokauError::OutOfRange {
+                    value,
+                    max: Self::COUNT,
+                }),
+            }
+        }
+    }
+
     // Example Range token
     #[derive(Debug, PartialEq, Clone, Copy)]
     pub struct TextTokens(pub u32);
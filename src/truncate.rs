@@ -0,0 +1,293 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::segment::Segmented;
+use crate::span::{SpanStack, SpanStep};
+
+/// What to do with a declared span that a cut falls inside
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpanPolicy {
+    /// Cut before the span's opening token instead, so it is kept whole or
+    /// not at all. A span too long to fit anywhere is closed early.
+    #[default]
+    Drop,
+    /// Keep as much as fits and append the closing token
+    Close,
+    /// Cut at the limit and leave the span open. When chunking, the next
+    /// chunk reopens it with the opening token.
+    Carry,
+}
+
+/// Part of a sequence produced by [`Truncator`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Ids of the chunk, including added opening and closing tokens
+    pub ids: Vec<u32>,
+    /// Range of the input covered by the chunk
+    pub range: Range<usize>,
+    /// Number of opening tokens prepended for spans open at the start
+    pub reopened: usize,
+    /// Input indices of the opening tokens of spans still open at the end,
+    /// outermost first
+    pub split: Vec<usize>,
+    /// Number of closing tokens appended for those spans
+    pub closed: usize,
+}
+
+/// Truncation and sliding-window chunking that keep the declared spans of a
+/// space (the `#[pair]` tokens) well-formed.
+///
+/// Chunks never exceed the maximum length, counting added opening and closing
+/// tokens, unless the spans open at a chunk's start leave no room for even
+/// one id.
+pub struct Truncator<S> {
+    max_len: usize,
+    policy: SpanPolicy,
+    snap: bool,
+    _space: PhantomData<fn() -> S>,
+}
+
+impl<S: Segmented> Truncator<S> {
+    /// Truncator for chunks of at most `max_len` ids
+    ///
+    /// # Panics
+    ///
+    /// If `max_len` is 0.
+    pub fn new(max_len: usize, policy: SpanPolicy) -> Self {
+        assert!(max_len > 0, "chunks must hold at least one id");
+        Truncator {
+            max_len,
+            policy,
+            snap: false,
+            _space: PhantomData,
+        }
+    }
+
+    /// Move cuts back to the start of the segment run they fall in, e.g. to
+    /// avoid splitting a run of audio tokens, unless that empties the chunk
+    pub fn snap_to_segments(mut self, snap: bool) -> Self {
+        self.snap = snap;
+        self
+    }
+
+    /// The leading chunk of `ids`. Everything from `range.end` on is cut.
+    pub fn truncate(&self, ids: &[u32]) -> Chunk {
+        self.chunk_at(ids, &Nesting::new::<S>(ids), 0)
+    }
+
+    /// Split `ids` into chunks, each starting `overlap` ids before the
+    /// previous one ended
+    pub fn chunks(&self, ids: &[u32], overlap: usize) -> Vec<Chunk> {
+        let nesting = Nesting::new::<S>(ids);
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        loop {
+            let chunk = self.chunk_at(ids, &nesting, start);
+            let end = chunk.range.end;
+            chunks.push(chunk);
+            if end >= ids.len() {
+                return chunks;
+            }
+            start = end.saturating_sub(overlap).max(start + 1);
+        }
+    }
+
+    fn chunk_at(&self, ids: &[u32], nesting: &Nesting, start: usize) -> Chunk {
+        let reopen = nesting.open_at(start);
+        let budget = self.max_len.saturating_sub(reopen.len()).max(1);
+        let mut end = (start + budget).min(ids.len());
+
+        if end < ids.len() {
+            if self.snap {
+                let run = run_start::<S>(ids, start, end);
+                if run > start {
+                    end = run;
+                }
+            }
+            if self.policy == SpanPolicy::Drop
+                && let Some(&outer) = nesting.open_at(end).iter().find(|open| **open >= start)
+                && outer > start
+            {
+                end = outer;
+            }
+            if self.policy != SpanPolicy::Carry {
+                while end > start + 1
+                    && reopen.len() + end - start + nesting.depth_at(end) > self.max_len
+                {
+                    end -= 1;
+                }
+            }
+        }
+
+        let split = nesting.open_at(end);
+        let mut chunk_ids: Vec<u32> = reopen.iter().map(|open| ids[*open]).collect();
+        chunk_ids.extend_from_slice(&ids[start..end]);
+        let mut closed = 0;
+        if end < ids.len() && self.policy != SpanPolicy::Carry {
+            for open in split.iter().rev() {
+                if let Some(close) = S::closer_of(ids[*open]) {
+                    chunk_ids.push(close);
+                    closed += 1;
+                }
+            }
+        }
+
+        Chunk {
+            ids: chunk_ids,
+            range: start..end,
+            reopened: reopen.len(),
+            split,
+            closed,
+        }
+    }
+}
+
+impl<S> Clone for Truncator<S> {
+    fn clone(&self) -> Self {
+        Truncator {
+            max_len: self.max_len,
+            policy: self.policy,
+            snap: self.snap,
+            _space: PhantomData,
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for Truncator<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Truncator")
+            .field("max_len", &self.max_len)
+            .field("policy", &self.policy)
+            .field("snap", &self.snap)
+            .finish()
+    }
+}
+
+// Span nesting of a whole sequence, so the spans open at any index can be
+// listed without rescanning
+struct Nesting {
+    // Innermost opening index open before each index, plus one past the end
+    innermost: Vec<Option<usize>>,
+    // Enclosing opening index of each opening token
+    parent: Vec<Option<usize>>,
+}
+
+impl Nesting {
+    fn new<S: Segmented>(ids: &[u32]) -> Self {
+        let mut stack = SpanStack::default();
+        let mut innermost = Vec::with_capacity(ids.len() + 1);
+        let mut parent = vec![None; ids.len()];
+        for (index, &id) in ids.iter().enumerate() {
+            innermost.push(stack.innermost());
+            if let SpanStep::Open = stack.push::<S>(index, id) {
+                parent[index] = innermost[index];
+            }
+        }
+        innermost.push(stack.innermost());
+        Nesting { innermost, parent }
+    }
+
+    // Opening indices of the spans open before `index`, outermost first
+    fn open_at(&self, index: usize) -> Vec<usize> {
+        let mut open = Vec::new();
+        let mut current = self.innermost[index];
+        while let Some(index) = current {
+            open.push(index);
+            current = self.parent[index];
+        }
+        open.reverse();
+        open
+    }
+
+    fn depth_at(&self, index: usize) -> usize {
+        std::iter::successors(self.innermost[index], |open| self.parent[*open]).count()
+    }
+}
+
+// Start of the run of same-kind ids that `end` would cut through, or `end`
+fn run_start<S: Segmented>(ids: &[u32], start: usize, end: usize) -> usize {
+    let kind = S::kind_of(ids[end]);
+    let mut run = end;
+    while run > start && S::kind_of(ids[run - 1]) == kind {
+        run -= 1;
+    }
+    run
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::tests::DynamicGingerSpace;
+
+    // TextStart=0, TextEnd=1, AudioStart=2, AudioEnd=3, dynamic from 1010
+    const IDS: [u32; 8] = [1010, 1011, 2, 1012, 1013, 1014, 3, 1015];
+
+    fn truncator(max_len: usize, policy: SpanPolicy) -> Truncator<DynamicGingerSpace> {
+        Truncator::new(max_len, policy)
+    }
+
+    #[test]
+    fn test_truncate_policies() {
+        let chunk = truncator(5, SpanPolicy::Drop).truncate(&IDS);
+        assert_eq!(chunk.ids, vec![1010, 1011]);
+        assert_eq!(chunk.range, 0..2);
+        assert!(chunk.split.is_empty());
+
+        let chunk = truncator(5, SpanPolicy::Close).truncate(&IDS);
+        assert_eq!(chunk.ids, vec![1010, 1011, 2, 1012, 3]);
+        assert_eq!(chunk.split, vec![2]);
+        assert_eq!(chunk.closed, 1);
+
+        let chunk = truncator(5, SpanPolicy::Carry).truncate(&IDS);
+        assert_eq!(chunk.ids, vec![1010, 1011, 2, 1012, 1013]);
+        assert_eq!(chunk.closed, 0);
+
+        // Short input is kept as is
+        let chunk = truncator(20, SpanPolicy::Close).truncate(&IDS);
+        assert_eq!(chunk.ids, IDS.to_vec());
+    }
+
+    #[test]
+    fn test_snap_to_segments() {
+        let chunk = truncator(5, SpanPolicy::Carry)
+            .snap_to_segments(true)
+            .truncate(&IDS);
+        assert_eq!(chunk.range, 0..3);
+    }
+
+    #[test]
+    fn test_chunks_carry() {
+        let chunks = truncator(4, SpanPolicy::Carry).chunks(&IDS, 1);
+        let ids: Vec<_> = chunks.iter().map(|chunk| chunk.ids.clone()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                vec![1010, 1011, 2, 1012],
+                vec![2, 1012, 1013, 1014],
+                vec![2, 1014, 3, 1015],
+            ]
+        );
+        assert_eq!(chunks[1].reopened, 1);
+    }
+
+    #[test]
+    fn test_chunks_drop() {
+        // The audio span moves whole into the second chunk
+        let chunks = truncator(6, SpanPolicy::Drop).chunks(&IDS, 0);
+        let ranges: Vec<_> = chunks.iter().map(|chunk| chunk.range.clone()).collect();
+        assert_eq!(ranges, vec![0..2, 2..8]);
+        assert!(chunks.iter().all(|chunk| chunk.closed == 0));
+    }
+
+    #[test]
+    fn test_long_span_is_closed() {
+        let chunks = truncator(3, SpanPolicy::Drop).chunks(&IDS, 0);
+        for chunk in &chunks {
+            assert!(chunk.ids.len() <= 3);
+            assert_eq!(
+                chunk.ids.iter().filter(|id| **id == 2).count(),
+                chunk.ids.iter().filter(|id| **id == 3).count()
+            );
+        }
+    }
+}
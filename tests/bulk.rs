@@ -0,0 +1,65 @@
+use tokau::bulk::{classify_into, decode_slice_into};
+use tokau::{DecodeError, Name, Position, SegmentTable, Segmented, Space, Token, TokenIter, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum Control {
+    Bos,
+    Eos,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(100)]
+struct AudioTokens(u32);
+
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum Open {
+    Control(Control),
+    Audio(AudioTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum Closed {
+    Control(Control),
+    Audio(AudioTokens),
+}
+
+#[test]
+fn test_classify_matches_kinds() {
+    // Long enough to cover full blocks and a remainder
+    let ids: Vec<u32> = (0..1000).map(|n| (n * 37) % 250).collect();
+    let mut out = vec![0u16; ids.len()];
+    classify_into::<Open, _>(&ids, &mut out);
+
+    let table = SegmentTable::<Open>::new();
+    let expected: Vec<_> = ids.iter().copied().kinds::<Open>().collect();
+    let found: Vec<_> = out
+        .iter()
+        .map(|index| table.kind_at(u32::from(*index)))
+        .collect();
+    assert_eq!(found, expected);
+    assert_eq!(table.kind_at(2), Open::DYNAMIC);
+}
+
+#[test]
+fn test_decode_slice_matches_decode() {
+    let ids: Vec<u32> = (0..300).collect();
+    let mut out = Vec::new();
+    decode_slice_into::<Open>(&ids, &mut out).unwrap();
+    let expected: Vec<Open> = ids
+        .iter()
+        .copied()
+        .decode::<Open>()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(out, expected);
+
+    let mut out = Vec::new();
+    assert!(matches!(
+        decode_slice_into::<Closed>(&ids, &mut out),
+        Err(DecodeError::OutOfRange { index: 102, .. })
+    ));
+    assert!(out.is_empty());
+}
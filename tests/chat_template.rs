@@ -0,0 +1,125 @@
+use tokau::{
+    ByteTokenizer, ChatRole, ChatTemplate, Frame, Message, Name, Position, Space, TemplateError,
+    Token,
+};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ChatToken {
+    #[name = "<|begin|>"]
+    Begin,
+    #[name = "<|im_start|>"]
+    ImStart,
+    #[name = "<|im_end|>"]
+    ImEnd,
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum ChatSpace {
+    Chat(ChatToken),
+    #[dynamic]
+    Text(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+// ChatML: <|im_start|>role\ncontent<|im_end|>\n
+impl ChatRole<ChatSpace> for Role {
+    const ROLES: &'static [Self] = &[Role::System, Role::User, Role::Assistant];
+
+    fn open(self) -> Frame<ChatSpace> {
+        let name = match self {
+            Role::System => "system\n",
+            Role::User => "user\n",
+            Role::Assistant => "assistant\n",
+        };
+        Frame::new().token(ChatToken::ImStart).text(name)
+    }
+
+    fn close(self) -> Frame<ChatSpace> {
+        Frame::new().token(ChatToken::ImEnd).text("\n")
+    }
+}
+
+fn template() -> ChatTemplate<ChatSpace, Role> {
+    ChatTemplate::new().with_begin(Frame::new().token(ChatToken::Begin))
+}
+
+#[test]
+fn test_round_trip() {
+    let messages = vec![
+        Message::new(Role::System, "Be brief."),
+        Message::new(Role::User, "héllo <|im_end|>"),
+        Message::new(Role::Assistant, "hi"),
+    ];
+    let ids = template().encode(&messages, &ByteTokenizer).unwrap();
+    assert_eq!(&ids[..2], &[0, 1]);
+
+    // Control literals in content stay text
+    assert_eq!(ids.iter().filter(|id| **id == 2).count(), 3);
+    assert_eq!(template().parse(&ids, &ByteTokenizer), Ok(messages));
+}
+
+#[test]
+fn test_prompt() {
+    let messages = [Message::new(Role::User, "2+2?")];
+    let prompt = template()
+        .encode_prompt(&messages, Role::Assistant, &ByteTokenizer)
+        .unwrap();
+    let mut completed = prompt.clone();
+    completed.extend([3 + b'4' as u32, 2, 3 + b'\n' as u32]);
+
+    assert_eq!(
+        template().parse(&prompt, &ByteTokenizer),
+        Err(TemplateError::Unterminated {
+            index: prompt.len() - 11
+        })
+    );
+    assert_eq!(
+        template().parse(&completed, &ByteTokenizer).unwrap()[1],
+        Message::new(Role::Assistant, "4")
+    );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Plain {
+    Text,
+}
+
+// No special tokens around messages at all
+impl ChatRole<ChatSpace> for Plain {
+    const ROLES: &'static [Self] = &[Plain::Text];
+
+    fn open(self) -> Frame<ChatSpace> {
+        Frame::new()
+    }
+
+    fn close(self) -> Frame<ChatSpace> {
+        Frame::new()
+    }
+}
+
+#[test]
+fn test_empty_frames() {
+    let template = ChatTemplate::<ChatSpace, Plain>::new();
+    let ids = template
+        .encode(&[Message::new(Plain::Text, "hi")], &ByteTokenizer)
+        .unwrap();
+    assert_eq!(
+        template.parse(&ids, &ByteTokenizer),
+        Ok(vec![Message::new(Plain::Text, "hi")])
+    );
+
+    // A reserved id cannot start a message, instead of matching forever
+    let mut with_special = ids.clone();
+    with_special.push(2);
+    assert_eq!(
+        template.parse(&with_special, &ByteTokenizer),
+        Err(TemplateError::Unexpected { index: 2, value: 2 })
+    );
+}
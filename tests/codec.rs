@@ -0,0 +1,67 @@
+use tokau::{ByteTokenizer, Codec, Name, Position, Space, Token, Tokenizer, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ChatToken {
+    #[name = "<|im_start|>"]
+    ImStart,
+    #[name = "<|im_end|>"]
+    ImEnd,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(3, format = "<|audio_{}|>")]
+struct AudioTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum ChatSpace {
+    Chat(ChatToken),
+    Audio(AudioTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+// Tokenizer over a fixed word list, words joined by spaces on decode
+struct WordTokenizer(Vec<&'static str>);
+
+impl Tokenizer for WordTokenizer {
+    fn encode(&self, text: &str) -> Vec<u32> {
+        text.split_whitespace()
+            .filter_map(|word| self.0.iter().position(|known| *known == word))
+            .map(|index| index as u32)
+            .collect()
+    }
+
+    fn decode(&self, ids: &[u32]) -> Vec<u8> {
+        let words: Vec<&str> = ids.iter().map(|id| self.0[*id as usize]).collect();
+        words.join(" ").into_bytes()
+    }
+}
+
+#[test]
+fn test_byte_codec() {
+    let codec = Codec::<ChatSpace, _>::new(ByteTokenizer);
+    let text = "<|im_start|>héllo<|im_end|>";
+    let ids = codec.encode(text).unwrap();
+    assert_eq!(ids[0], 0);
+    assert_eq!(ids[ids.len() - 1], 1);
+    assert_eq!(codec.decode(&ids).unwrap(), text);
+
+    // Range tokens render with their format pattern
+    assert_eq!(
+        codec.decode(&[0, 3, 4, 1]).unwrap(),
+        "<|im_start|><|audio_1|><|audio_2|><|im_end|>"
+    );
+}
+
+#[test]
+fn test_custom_tokenizer() {
+    let codec = Codec::<ChatSpace, _>::new(WordTokenizer(vec!["hello", "world"]));
+    let ids = codec.encode("<|im_start|>hello world<|im_end|>").unwrap();
+    assert_eq!(ids, vec![0, 5, 6, 1]);
+    assert_eq!(
+        codec.decode(&ids).unwrap(),
+        "<|im_start|>hello world<|im_end|>"
+    );
+    assert_eq!(codec.tokenizer().0.len(), 2);
+}
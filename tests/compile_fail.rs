@@ -0,0 +1,6 @@
+// Layouts and declarations the derives reject at compile time
+#[test]
+fn test_compile_fail() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
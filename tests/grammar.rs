@@ -0,0 +1,85 @@
+use tokau::logits::mask;
+use tokau::{Grammar, Name, Position, Space, Token, UnexpectedToken, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ControlToken {
+    AudioStart,
+    AudioEnd,
+    Stop,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(4)]
+struct AudioTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum MySpace {
+    Control(ControlToken),
+    Audio(AudioTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+// Text until AudioStart, then only audio until AudioEnd, Stop ends the sequence
+fn grammar() -> Grammar<MySpace> {
+    let mut builder = Grammar::<MySpace>::builder();
+    let text = builder.state();
+    let audio = builder.state();
+    let done = builder.accepting_state();
+    builder
+        .on_dynamic(text, text)
+        .on_token(text, ControlToken::AudioStart, audio)
+        .unwrap()
+        .on_segment::<AudioTokens>(audio, audio)
+        .on_token(audio, ControlToken::AudioEnd, text)
+        .unwrap()
+        .on_name(text, "Stop", done)
+        .unwrap();
+    builder.build(text)
+}
+
+#[test]
+fn test_drives_logit_mask() {
+    let grammar = grammar();
+    let mut matcher = grammar.matcher();
+
+    let mut logits = vec![0.0f32; 10];
+    mask(&mut logits, matcher.allowed());
+    let finite: Vec<usize> = (0..10).filter(|&id| logits[id].is_finite()).collect();
+    assert_eq!(finite, vec![0, 2, 7, 8, 9]);
+
+    matcher.step(0).unwrap();
+    let mut logits = vec![0.0f32; 10];
+    mask(&mut logits, matcher.allowed());
+    let finite: Vec<usize> = (0..10).filter(|&id| logits[id].is_finite()).collect();
+    assert_eq!(finite, vec![1, 3, 4, 5, 6]);
+}
+
+#[test]
+fn test_unclosed_span_is_not_accepting() {
+    let grammar = grammar();
+    let mut matcher = grammar.matcher();
+
+    matcher.step_all([8, 0, 3, 4]).unwrap();
+    assert!(!matcher.is_accepting());
+    assert_eq!(
+        matcher.step(2),
+        Err(UnexpectedToken {
+            index: 4,
+            value: 2,
+            state: 1,
+        })
+    );
+
+    matcher.step_all([1, 9, 2]).unwrap();
+    assert!(matcher.is_accepting());
+    assert!(matcher.allowed().is_empty());
+}
+
+#[test]
+fn test_unknown_name() {
+    let mut builder = Grammar::<MySpace>::builder();
+    let state = builder.state();
+    assert!(builder.on_name(state, "Pause", state).is_err());
+}
@@ -0,0 +1,219 @@
+use tokau::{DecodeError, Name, Position, Space, TokauError, Token, TokenIter, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ControlToken {
+    Start,
+    Stop,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(100)]
+struct TextTokens(u32);
+
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum MySpace {
+    Control(ControlToken),
+    Text(TextTokens),
+    #[dynamic]
+    Vocab(u32),
+}
+
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum ClosedSpace {
+    Control(ControlToken),
+}
+
+#[test]
+fn test_decode_i64_labels() {
+    // PyTorch-style labels with the -100 ignore index
+    let labels: Vec<i64> = vec![0, 5, -100, 102];
+
+    let decoded: Vec<Result<MySpace, TokauError>> = labels.iter().copied().decode().collect();
+    assert_eq!(
+        decoded,
+        vec![
+            Ok(MySpace::Control(ControlToken::Start)),
+            Ok(MySpace::Text(TextTokens(3))),
+            Err(TokauError::InvalidId { value: -100 }),
+            Ok(MySpace::Vocab(0)),
+        ]
+    );
+
+    assert_eq!(
+        labels.iter().copied().try_decode_all::<MySpace>(),
+        Err(DecodeError::InvalidId {
+            index: 2,
+            value: -100,
+        })
+    );
+
+    let lossy: Vec<MySpace> = labels
+        .into_iter()
+        .decode_lossy(MySpace::Control(ControlToken::Stop))
+        .collect();
+    assert_eq!(lossy[2], MySpace::Control(ControlToken::Stop));
+}
+
+#[test]
+fn test_decode_u16_dataset() {
+    let ids: Vec<u16> = vec![1, 50, 200];
+    let decoded = ids.into_iter().try_decode_all::<MySpace>();
+    assert_eq!(
+        decoded,
+        Ok(vec![
+            MySpace::Control(ControlToken::Stop),
+            MySpace::Text(TextTokens(48)),
+            MySpace::Vocab(98),
+        ])
+    );
+
+    // Segment context is kept for out of range IDs in other widths
+    let decoded = vec![0u64, 2].into_iter().try_decode_all::<ClosedSpace>();
+    assert_eq!(
+        decoded,
+        Err(DecodeError::OutOfRange {
+            index: 1,
+            value: 2,
+            max: 2,
+            nearest: Some("Control"),
+        })
+    );
+
+    // Values past u32::MAX are rejected rather than truncated
+    let decoded = vec![u32::MAX as u64 + 1]
+        .into_iter()
+        .try_decode_all::<MySpace>();
+    assert_eq!(
+        decoded,
+        Err(DecodeError::InvalidId {
+            index: 0,
+            value: u32::MAX as i128 + 1,
+        })
+    );
+}
+
+#[test]
+fn test_remainders_and_after_reserved() {
+    let ids: Vec<i64> = vec![0, 102, -100, 150];
+    let remainders: Vec<Result<u32, DecodeError>> =
+        ids.into_iter().remainders::<MySpace>().collect();
+    assert_eq!(
+        remainders,
+        vec![
+            Ok(0),
+            Err(DecodeError::InvalidId {
+                index: 2,
+                value: -100,
+            }),
+            Ok(48),
+        ]
+    );
+
+    // Every u16 is a valid id, so there is nothing to report
+    let shifted: Vec<u32> = vec![0u16, 10]
+        .into_iter()
+        .after_reserved::<MySpace>()
+        .collect();
+    assert_eq!(shifted, vec![102, 112]);
+
+    let shifted: Vec<Result<u32, DecodeError>> = vec![-1i32, 1]
+        .into_iter()
+        .after_reserved::<MySpace>()
+        .collect();
+    assert_eq!(
+        shifted,
+        vec![
+            Err(DecodeError::InvalidId {
+                index: 0,
+                value: -1
+            }),
+            Ok(103)
+        ]
+    );
+}
+
+#[test]
+fn test_checked_after_reserved() {
+    let values: Vec<u16> = vec![0, 10, u16::MAX];
+    let shifted: Vec<Result<u16, DecodeError>> = values
+        .into_iter()
+        .checked_after_reserved::<MySpace>()
+        .collect();
+    assert_eq!(
+        shifted,
+        vec![
+            Ok(102),
+            Ok(112),
+            // Fits in u32 but not back in u16
+            Err(DecodeError::InvalidId {
+                index: 2,
+                value: u16::MAX as i128 + 102,
+            }),
+        ]
+    );
+
+    let shifted: Vec<Result<i32, DecodeError>> = vec![-1i32, 1]
+        .into_iter()
+        .checked_after_reserved::<MySpace>()
+        .collect();
+    assert_eq!(
+        shifted,
+        vec![
+            Err(DecodeError::InvalidId {
+                index: 0,
+                value: -1
+            }),
+            Ok(103)
+        ]
+    );
+}
+
+#[test]
+fn test_filters_on_other_widths() {
+    let ids: Vec<i64> = vec![-100, 0, 3, 1, 500];
+    let invalid = DecodeError::InvalidId {
+        index: 0,
+        value: -100,
+    };
+
+    // Invalid ids are reported, not confused with ids outside the space
+    let kinds: Vec<Result<Option<MySpaceKind>, DecodeError>> =
+        ids.iter().copied().kinds::<MySpace>().collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Err(invalid),
+            Ok(Some(MySpaceKind::Control)),
+            Ok(Some(MySpaceKind::Text)),
+            Ok(Some(MySpaceKind::Control)),
+            Ok(Some(MySpaceKind::Vocab)),
+        ]
+    );
+
+    let controls: Vec<Result<ControlToken, DecodeError>> = ids
+        .iter()
+        .copied()
+        .only::<MySpace, ControlToken>()
+        .collect();
+    assert_eq!(
+        controls,
+        vec![
+            Err(invalid),
+            Ok(ControlToken::Start),
+            Ok(ControlToken::Stop)
+        ]
+    );
+
+    let positions: Result<Vec<usize>, DecodeError> = ids
+        .into_iter()
+        .positions_of::<MySpace, TextTokens>()
+        .collect();
+    assert_eq!(positions, Err(invalid));
+
+    let positions: Vec<usize> = vec![0u16, 3, 1]
+        .into_iter()
+        .positions_of::<MySpace, TextTokens>()
+        .collect();
+    assert_eq!(positions, vec![1]);
+}
@@ -0,0 +1,73 @@
+use tokau::{Name, Position, Space, Token, TokenIter, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ControlToken {
+    Start,
+    Stop,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(100)]
+struct TextTokens(u32);
+
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum MySpace {
+    Control(ControlToken),
+    Text(TextTokens),
+    #[dynamic]
+    Vocab(u32),
+}
+
+#[test]
+fn test_encode_tokens() {
+    let ids: Vec<u32> = vec![ControlToken::Stop, ControlToken::Start]
+        .into_iter()
+        .encode::<MySpace>()
+        .collect();
+    assert_eq!(ids, vec![1, 0]);
+
+    let ids: Vec<u32> = vec![TextTokens(0), TextTokens(99)]
+        .into_iter()
+        .encode::<MySpace>()
+        .collect();
+    assert_eq!(ids, vec![2, 101]);
+}
+
+#[test]
+fn test_encode_space_values() {
+    let values = vec![
+        MySpace::Control(ControlToken::Start),
+        MySpace::Text(TextTokens(5)),
+        MySpace::Vocab(0),
+    ];
+    let ids: Vec<u32> = values.clone().into_iter().encode::<MySpace>().collect();
+    assert_eq!(ids, vec![0, 7, 102]);
+
+    // Encoding then decoding gives back the same values
+    let decoded: Vec<MySpace> = ids.into_iter().decode_total::<MySpace>().collect();
+    assert_eq!(decoded, values);
+}
+
+#[test]
+fn test_typed_extraction() {
+    let ids: Vec<u32> = vec![0, 2, 102, 1, 50, 200];
+
+    let texts: Vec<TextTokens> = ids
+        .clone()
+        .into_iter()
+        .only::<MySpace, TextTokens>()
+        .collect();
+    assert_eq!(texts, vec![TextTokens(0), TextTokens(48)]);
+
+    let stops: Vec<usize> = ids
+        .clone()
+        .into_iter()
+        .positions_of::<MySpace, ControlToken>()
+        .collect();
+    assert_eq!(stops, vec![0, 3]);
+
+    let (reserved, dynamic) = ids.into_iter().partition_reserved::<MySpace>();
+    assert_eq!(reserved, vec![0, 2, 1, 50]);
+    assert_eq!(dynamic, vec![102, 200]);
+}
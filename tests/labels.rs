@@ -0,0 +1,49 @@
+use tokau::{IGNORE_INDEX, Loss, LossPolicy, Name, Position, SequenceBuilder, Space, Token, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+#[pair(UserStart, UserEnd)]
+#[pair(AssistantStart, AssistantEnd)]
+enum Role {
+    UserStart,
+    UserEnd,
+    AssistantStart,
+    AssistantEnd,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(4)]
+struct AudioTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum Chat {
+    Role(Role),
+    Audio(AudioTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+#[test]
+fn test_mask_user_turns() {
+    let mut builder = SequenceBuilder::<Chat>::new();
+    builder
+        .span(Role::UserStart, Role::UserEnd, |b| {
+            b.dynamic_ids([10, 11]);
+        })
+        .span(Role::AssistantStart, Role::AssistantEnd, |b| {
+            b.dynamic(12).tokens([AudioTokens(0), AudioTokens(1)]);
+        });
+    let ids = builder.build();
+
+    // Ignore user turns, down-weight audio conditioning, never train on the
+    // assistant's opening token
+    let policy = LossPolicy::<Chat>::new()
+        .span(Role::UserStart, Role::UserEnd, Loss::Ignore)
+        .kind(ChatKind::Audio, Loss::Weight(0.25))
+        .token(Role::AssistantStart, Loss::Ignore);
+    let (labels, weights) = policy.labels(&ids).unwrap();
+
+    let i = IGNORE_INDEX;
+    assert_eq!(labels, vec![i, i, i, i, i, 20, 4, 5, 3]);
+    assert_eq!(weights, vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.25, 0.25, 1.0]);
+}
@@ -0,0 +1,99 @@
+use tokau::logits::{bias_segment, mask};
+use tokau::{LogitBias, Name, Position, Space, Token, TokenSet, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ControlToken {
+    Start,
+    Stop,
+    Pause,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(10)]
+struct TextTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum MySpace {
+    Control(ControlToken),
+    Text(TextTokens),
+    #[dynamic]
+    Vocab(u32),
+}
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ChatToken {
+    #[name = "<|im_start|>"]
+    ImStart,
+    #[name = "<|im_end|>"]
+    ImEnd,
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum ChatSpace {
+    Chat(ChatToken),
+    Text(TextTokens),
+}
+
+#[test]
+fn test_token_names() {
+    assert_eq!(ControlToken::NAMES, &["Start", "Stop", "Pause"]);
+    assert_eq!(ControlToken::Pause.name(), Some("Pause"));
+    assert_eq!(TextTokens(3).name(), None);
+}
+
+#[test]
+fn test_mask_and_bias() {
+    let mut logits = vec![0.0f32; 20];
+    bias_segment::<MySpace, ControlToken>(&mut logits, 2.0);
+    mask(
+        &mut logits,
+        &TokenSet::<MySpace>::segment::<TextTokens>()
+            .with_token(ControlToken::Stop)
+            .unwrap(),
+    );
+
+    assert_eq!(logits[0], f32::NEG_INFINITY);
+    assert_eq!(logits[1], 2.0);
+    assert_eq!(logits[3], 0.0);
+    assert_eq!(logits[13], f32::NEG_INFINITY);
+}
+
+#[test]
+fn test_bias_by_name() {
+    // e.g. read from a sampling config
+    let config = vec![("Control".to_string(), 2.0), ("Stop".to_string(), -5.0)];
+    let bias = LogitBias::<MySpace>::from_names(config).unwrap();
+
+    let mut logits = vec![0.0f32; 14];
+    bias.apply(&mut logits);
+    assert_eq!(&logits[..4], &[2.0, -3.0, 2.0, 0.0]);
+
+    // The dynamic tail is addressed by its variant name
+    let bias = LogitBias::<MySpace>::from_names([("Vocab", 1.0)]).unwrap();
+    let mut logits = vec![0.0f32; 16];
+    bias.apply(&mut logits);
+    assert_eq!(&logits[12..], &[0.0, 1.0, 1.0, 1.0]);
+
+    let err = LogitBias::<MySpace>::from_names([("Audio", 1.0)]).unwrap_err();
+    assert_eq!(err.to_string(), "No token or segment named \"Audio\"");
+}
+
+#[test]
+fn test_bias_by_renamed_token() {
+    // Renamed tokens match both their literal and their variant name
+    let bias =
+        LogitBias::<ChatSpace>::from_names([("ImStart", 1.0), ("<|im_end|>", -1.0)]).unwrap();
+    let mut logits = vec![0.0f32; 3];
+    bias.apply(&mut logits);
+    assert_eq!(logits, vec![1.0, -1.0, 0.0]);
+
+    let bias = LogitBias::<ChatSpace>::from_names([("Chat", 2.0)]).unwrap();
+    let mut logits = vec![0.0f32; 3];
+    bias.apply(&mut logits);
+    assert_eq!(logits, vec![2.0, 2.0, 0.0]);
+
+    // Closed spaces have no dynamic segment to name
+    assert!(LogitBias::<ChatSpace>::from_names([("Vocab", 1.0)]).is_err());
+}
@@ -0,0 +1,171 @@
+use tokau::{
+    ClosedSpace, DefaultTokenSpace, Name, OpenSpace, Position, Space, TokauError, Token, TokenIter,
+    TokenSpace, range,
+};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ControlToken {
+    Start,
+    Stop,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(10)]
+struct TextTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum OpenTestSpace {
+    Control(ControlToken),
+    Text(TextTokens),
+    #[dynamic]
+    Vocab(u32),
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum ClosedTestSpace {
+    Control(ControlToken),
+    Text(TextTokens),
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum OnlyDynamicSpace {
+    #[dynamic]
+    Dynamic(u32),
+}
+
+fn assert_open<S: OpenSpace>() {}
+fn assert_closed<S: ClosedSpace>() {}
+
+#[test]
+fn test_marker_traits() {
+    assert_open::<OpenTestSpace>();
+    assert_open::<OnlyDynamicSpace>();
+    assert_open::<DefaultTokenSpace<ControlToken>>();
+    assert_closed::<ClosedTestSpace>();
+}
+
+#[test]
+fn test_decode_total() {
+    assert_eq!(
+        OpenTestSpace::decode_total(0),
+        OpenTestSpace::Control(ControlToken::Start)
+    );
+    assert_eq!(
+        OpenTestSpace::decode_total(11),
+        OpenTestSpace::Text(TextTokens(9))
+    );
+    assert_eq!(OpenTestSpace::decode_total(12), OpenTestSpace::Vocab(0));
+    assert_eq!(
+        OpenTestSpace::decode_total(u32::MAX),
+        OpenTestSpace::Vocab(u32::MAX - 12)
+    );
+    assert_eq!(
+        OnlyDynamicSpace::decode_total(0),
+        OnlyDynamicSpace::Dynamic(0)
+    );
+
+    // try_from never fails for open spaces of derived tokens and agrees with decode_total
+    for id in [0, 1, 2, 11, 12, 1000, u32::MAX] {
+        assert_eq!(
+            OpenTestSpace::try_from(id),
+            Ok(OpenTestSpace::decode_total(id))
+        );
+    }
+
+    // Closed spaces still report out of range ids
+    assert_eq!(
+        ClosedTestSpace::try_from(12),
+        Err(TokauError::OutOfRange { value: 12, max: 12 })
+    );
+}
+
+#[test]
+fn test_decode_total_iter() {
+    let decoded: Vec<OpenTestSpace> = vec![1, 5, 12, 20]
+        .into_iter()
+        .decode_total::<OpenTestSpace>()
+        .collect();
+    assert_eq!(
+        decoded,
+        vec![
+            OpenTestSpace::Control(ControlToken::Stop),
+            OpenTestSpace::Text(TextTokens(3)),
+            OpenTestSpace::Vocab(0),
+            OpenTestSpace::Vocab(8),
+        ]
+    );
+
+    let default: Vec<DefaultTokenSpace<ControlToken>> = vec![0, 2]
+        .into_iter()
+        .decode_total::<DefaultTokenSpace<ControlToken>>()
+        .collect();
+    assert_eq!(
+        default,
+        vec![
+            DefaultTokenSpace::Token(ControlToken::Start),
+            DefaultTokenSpace::Dynamic(0),
+        ]
+    );
+    assert_eq!(OpenTestSpace::RESERVED, 12);
+}
+
+// Hand-written token whose TryFrom leaves id 1 unclaimed
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct SparseToken(u32);
+
+impl Token for SparseToken {
+    const COUNT: u32 = 3;
+
+    fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl TryFrom<u32> for SparseToken {
+    type Error = TokauError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 | 2 => Ok(SparseToken(value)),
+            _ => Err(TokauError::InvalidId {
+                value: value.into(),
+            }),
+        }
+    }
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum SparseSpace {
+    Sparse(SparseToken),
+    #[dynamic]
+    Dynamic(u32),
+}
+
+#[test]
+fn test_unclaimed_reserved_id() {
+    assert_eq!(
+        SparseSpace::try_from(0),
+        Ok(SparseSpace::Sparse(SparseToken(0)))
+    );
+    assert_eq!(
+        SparseSpace::try_from(1),
+        Err(TokauError::InvalidId { value: 1 })
+    );
+    assert_eq!(SparseSpace::try_from(3), Ok(SparseSpace::Dynamic(0)));
+
+    assert_eq!(
+        DefaultTokenSpace::<SparseToken>::try_from(1),
+        Err(TokauError::InvalidId { value: 1 })
+    );
+    assert_eq!(
+        DefaultTokenSpace::<SparseToken>::try_from(3),
+        Ok(DefaultTokenSpace::Dynamic(0))
+    );
+}
+
+#[test]
+#[should_panic(expected = "Value 1 is not a representable token ID")]
+fn test_decode_total_unclaimed_reserved_id() {
+    SparseSpace::decode_total(1);
+}
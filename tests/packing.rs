@@ -0,0 +1,43 @@
+use tokau::{Name, Packer, Position, Space, Token, TokenSpace};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum Control {
+    Eos,
+    Pad,
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum Corpus {
+    Control(Control),
+    #[dynamic]
+    Text(u32),
+}
+
+#[test]
+fn test_pack_corpus() {
+    let documents: Vec<Vec<u32>> = (1..=5)
+        .map(|len| (0..len).map(Corpus::after_reserved).collect())
+        .collect();
+    let packer = Packer::<Corpus>::new(8, Control::Eos, Control::Pad);
+    let rows = packer.pack(&documents);
+
+    // Every row has the same shape, and no document is lost
+    assert!(rows.iter().all(|row| row.ids.len() == 8
+        && row.attention_mask.len() == 8
+        && row.position_ids.len() == 8));
+    let eos = Corpus::position_of(Control::Eos);
+    let separators: usize = rows
+        .iter()
+        .map(|row| row.ids.iter().filter(|id| **id == eos).count())
+        .sum();
+    assert_eq!(separators, 5);
+
+    // Padding is masked out and only appears after the real ids
+    for row in &rows {
+        let real = row.real_len();
+        assert!(row.attention_mask[..real].iter().all(|m| *m == 1));
+        assert!(row.attention_mask[real..].iter().all(|m| *m == 0));
+        assert!(row.ids[real..].iter().all(|id| *id == 1));
+    }
+}
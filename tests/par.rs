@@ -0,0 +1,51 @@
+#![cfg(feature = "rayon")]
+
+use tokau::par::{par_classify_into, par_counts, par_decode, par_remainders};
+use tokau::{DecodeError, Name, Position, Space, Token, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum Control {
+    Bos,
+    Eos,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(100)]
+struct AudioTokens(u32);
+
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum Shard {
+    Control(Control),
+    Audio(AudioTokens),
+}
+
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum OpenShard {
+    Control(Control),
+    Audio(AudioTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+#[test]
+fn test_shard() {
+    let ids: Vec<u32> = (0..200_000u32).map(|n| n % 150).collect();
+
+    let tokens = par_decode::<OpenShard>(&ids).unwrap();
+    assert_eq!(tokens[1], OpenShard::Control(Control::Eos));
+    assert_eq!(tokens[149], OpenShard::Text(47));
+    assert_eq!(par_remainders::<OpenShard>(&ids)[..3], [0, 1, 2]);
+
+    let mut types = vec![0u8; ids.len()];
+    par_classify_into::<OpenShard, _>(&ids, &mut types);
+    assert_eq!(types[..4], [0, 0, 1, 1]);
+    // 1333 full cycles of 150 ids, 48 of them dynamic
+    assert_eq!(par_counts::<OpenShard>(&ids)[2], 1333 * 48);
+
+    // The closed space rejects the first id past its 102 tokens
+    assert!(matches!(
+        par_decode::<Shard>(&ids),
+        Err(DecodeError::OutOfRange { index: 102, .. })
+    ));
+}
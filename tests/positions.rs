@@ -0,0 +1,40 @@
+use tokau::{Name, Position, PositionRules, Space, Step, Token, TokenSpace, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum Vision {
+    ImageStart,
+    ImageEnd,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(1024)]
+struct Patches(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum Multimodal {
+    Vision(Vision),
+    Patch(Patches),
+    #[dynamic]
+    Text(u32),
+}
+
+#[test]
+fn test_image_grid_between_text() {
+    let text = Multimodal::after_reserved;
+    let open = Multimodal::position_of(Vision::ImageStart);
+    let close = Multimodal::position_of(Vision::ImageEnd);
+    let patches = (0..6).map(|n| Multimodal::position_of(Patches(n)));
+
+    let mut ids = vec![text(0), open];
+    ids.extend(patches);
+    ids.extend([close, text(1)]);
+
+    let rules = PositionRules::<Multimodal>::new().segment::<Patches>(Step::Grid { width: 3 });
+    let positions = rules.positions(&ids).unwrap();
+
+    assert_eq!(positions.len(), 10);
+    assert_eq!(positions.temporal, vec![0, 1, 2, 2, 2, 2, 2, 2, 5, 6]);
+    assert_eq!(positions.height, vec![0, 1, 2, 2, 2, 3, 3, 3, 5, 6]);
+    assert_eq!(positions.width, vec![0, 1, 2, 3, 4, 2, 3, 4, 5, 6]);
+}
@@ -0,0 +1,78 @@
+use tokau::render::{ByteLevelVocab, Renderer, render};
+use tokau::{DecodeError, Name, Position, Render, Space, Token, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ControlToken {
+    Start,
+    Stop,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(4, format = "<audio_{}>")]
+struct AudioTokens(u32);
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(2)]
+struct PlainTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum MySpace {
+    Control(ControlToken),
+    Audio(AudioTokens),
+    Plain(PlainTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum ClosedSpace {
+    Control(ControlToken),
+}
+
+fn rendered(token: impl Render) -> String {
+    let mut out = String::new();
+    token.render(&mut out);
+    out
+}
+
+#[test]
+fn test_token_render() {
+    assert_eq!(rendered(ControlToken::Stop), "Stop");
+    assert_eq!(rendered(AudioTokens(3)), "<audio_3>");
+    assert_eq!(rendered(PlainTokens(1)), "PlainTokens(1)");
+    assert_eq!(rendered(MySpace::Audio(AudioTokens(0))), "<audio_0>");
+    assert_eq!(rendered(MySpace::Text(7)), "");
+}
+
+#[test]
+fn test_render_sequence() {
+    let vocab = ByteLevelVocab::new(["hi", "Ġthere"]);
+    assert_eq!(
+        render::<MySpace, _>(&[0, 8, 9, 2, 5, 1], &vocab),
+        Ok("Starthi there<audio_0><audio_3>Stop".to_string())
+    );
+    assert_eq!(
+        render::<ClosedSpace, _>(&[0, 2], &vocab),
+        Err(DecodeError::OutOfRange {
+            index: 1,
+            value: 2,
+            max: 2,
+            nearest: Some("Control"),
+        })
+    );
+}
+
+#[test]
+fn test_streaming_render() {
+    // "€" is e2 82 ac, spread over three tokens
+    let vocab: Vec<Vec<u8>> = vec![vec![0xe2], vec![0x82], vec![0xac, b'!']];
+    let mut renderer = Renderer::<MySpace, _>::new(&vocab);
+    let mut out = String::new();
+    for id in [8, 9] {
+        renderer.push(id, &mut out).unwrap();
+        assert_eq!(out, "");
+    }
+    renderer.push(10, &mut out).unwrap();
+    assert_eq!(out, "€!");
+}
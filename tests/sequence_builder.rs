@@ -0,0 +1,46 @@
+use tokau::{ByteTokenizer, Name, Position, SequenceBuilder, Space, Token, TokenIter, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+#[pair(UserStart, UserEnd)]
+#[pair(AudioStart, AudioEnd)]
+enum Control {
+    UserStart,
+    UserEnd,
+    AudioStart,
+    AudioEnd,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(8)]
+struct AudioTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum Speech {
+    Control(Control),
+    Audio(AudioTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+#[test]
+fn test_nested_spans() {
+    let mut builder = SequenceBuilder::<Speech, f32>::new();
+    builder.span(Control::UserStart, Control::UserEnd, |b| {
+        b.text("hi", &ByteTokenizer);
+        b.with_meta(1.0, |b| {
+            b.span(Control::AudioStart, Control::AudioEnd, |b| {
+                b.tokens((0..3).map(AudioTokens));
+            });
+        });
+    });
+    let (ids, weights) = builder.build_with_meta();
+
+    assert_eq!(ids, vec![0, 12 + 104, 12 + 105, 2, 4, 5, 6, 3, 1]);
+    assert_eq!(weights, vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0]);
+
+    // Spans come out balanced
+    let spans: Vec<_> = ids.into_iter().spans::<Speech>().collect();
+    assert_eq!(spans.len(), 2);
+    assert!(spans.iter().all(Result::is_ok));
+}
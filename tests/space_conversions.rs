@@ -0,0 +1,128 @@
+use tokau::{Name, Position, Space, Token, TokenIter, TokenSpace, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ControlToken {
+    Start,
+    Stop,
+    Pause,
+}
+
+#[derive(Debug, PartialEq)]
+#[range(1000)]
+struct TextTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum MySpace {
+    Control(ControlToken),
+    Text(TextTokens),
+    #[dynamic]
+    Vocab(u32),
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum SingleSpace {
+    Control(ControlToken),
+}
+
+#[test]
+fn test_from_token() {
+    assert_eq!(
+        MySpace::from(ControlToken::Stop),
+        MySpace::Control(ControlToken::Stop)
+    );
+    assert_eq!(MySpace::from(TextTokens(7)), MySpace::Text(TextTokens(7)));
+
+    let space: MySpace = ControlToken::Pause.into();
+    assert_eq!(space, MySpace::Control(ControlToken::Pause));
+}
+
+#[test]
+fn test_try_from_space() {
+    assert_eq!(
+        ControlToken::try_from(MySpace::Control(ControlToken::Start)),
+        Ok(ControlToken::Start)
+    );
+    assert_eq!(
+        TextTokens::try_from(MySpace::Text(TextTokens(42))),
+        Ok(TextTokens(42))
+    );
+
+    // The mismatched space value is handed back
+    assert_eq!(
+        ControlToken::try_from(MySpace::Vocab(3)),
+        Err(MySpace::Vocab(3))
+    );
+    assert_eq!(
+        TextTokens::try_from(MySpace::Control(ControlToken::Stop)),
+        Err(MySpace::Control(ControlToken::Stop))
+    );
+
+    // Single-variant spaces always convert
+    assert_eq!(
+        ControlToken::try_from(SingleSpace::Control(ControlToken::Stop)),
+        Ok(ControlToken::Stop)
+    );
+}
+
+#[test]
+fn test_into_id() {
+    assert_eq!(u32::from(MySpace::Control(ControlToken::Start)), 0);
+    assert_eq!(u32::from(MySpace::Text(TextTokens(0))), 3);
+    assert_eq!(u32::from(MySpace::Vocab(0)), MySpace::RESERVED);
+
+    // Round trip through ids without requiring Copy
+    for id in [0, 2, 3, 1002, 1003, 5000] {
+        let space = MySpace::try_from(id).unwrap();
+        assert_eq!(u32::from(space), id);
+    }
+}
+
+#[test]
+fn test_get() {
+    let control = MySpace::Control(ControlToken::Pause);
+    assert_eq!(control.get::<ControlToken>(), Some(ControlToken::Pause));
+    assert_eq!(control.get::<TextTokens>(), None);
+
+    let text = MySpace::Text(TextTokens(999));
+    assert_eq!(text.get::<TextTokens>(), Some(TextTokens(999)));
+    assert_eq!(text.get::<ControlToken>(), None);
+
+    let vocab = MySpace::Vocab(0);
+    assert_eq!(vocab.get::<ControlToken>(), None);
+    assert_eq!(vocab.get::<TextTokens>(), None);
+}
+
+// Generic code can move between tokens, spaces and ids without naming variants
+fn encode_all<S, T>(tokens: Vec<T>) -> Vec<u32>
+where
+    T: Token,
+    S: TokenSpace + From<T>,
+    u32: From<S>,
+{
+    tokens
+        .into_iter()
+        .map(|token| u32::from(S::from(token)))
+        .collect()
+}
+
+fn extract_all<S, T>(ids: Vec<u32>) -> Vec<T>
+where
+    S: TokenSpace,
+    T: TryFrom<S>,
+{
+    ids.into_iter()
+        .decode::<S>()
+        .filter_map(Result::ok)
+        .filter_map(|space| T::try_from(space).ok())
+        .collect()
+}
+
+#[test]
+fn test_generic_conversions() {
+    let ids = encode_all::<MySpace, _>(vec![TextTokens(1), TextTokens(2)]);
+    assert_eq!(ids, vec![4, 5]);
+
+    let controls: Vec<ControlToken> = extract_all::<MySpace, _>(vec![0, 4, 2, 1500]);
+    assert_eq!(controls, vec![ControlToken::Start, ControlToken::Pause]);
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use tokau::{Name, Position, Segmented, Space, Token, TokenIter, TokenSpace, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ControlToken {
+    Start,
+    Stop,
+    Pause,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(1000)]
+struct TextTokens(u32);
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(0)]
+struct EmptyTokens(u32);
+
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum MySpace {
+    Control(ControlToken),
+    Text(TextTokens),
+    #[dynamic]
+    Vocab(u32),
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum ClosedSpace {
+    Empty(EmptyTokens),
+    Control(ControlToken),
+}
+
+#[test]
+fn test_kind_of() {
+    assert_eq!(MySpace::kind_of(0), Some(MySpaceKind::Control));
+    assert_eq!(MySpace::kind_of(2), Some(MySpaceKind::Control));
+    assert_eq!(MySpace::kind_of(3), Some(MySpaceKind::Text));
+    assert_eq!(MySpace::kind_of(1002), Some(MySpaceKind::Text));
+    assert_eq!(MySpace::kind_of(1003), Some(MySpaceKind::Vocab));
+    assert_eq!(MySpace::kind_of(u32::MAX), Some(MySpaceKind::Vocab));
+
+    // Closed spaces have no kind past RESERVED, and empty segments own no ids
+    assert_eq!(ClosedSpace::kind_of(0), Some(ClosedSpaceKind::Control));
+    assert_eq!(ClosedSpace::kind_of(2), Some(ClosedSpaceKind::Control));
+    assert_eq!(ClosedSpace::kind_of(3), None);
+}
+
+#[test]
+fn test_kind_of_value() {
+    assert_eq!(
+        MySpace::Control(ControlToken::Stop).kind(),
+        MySpaceKind::Control
+    );
+    assert_eq!(MySpace::Text(TextTokens(5)).kind(), MySpaceKind::Text);
+    assert_eq!(MySpace::Vocab(5).kind(), MySpaceKind::Vocab);
+
+    // kind_of agrees with decoding then taking the kind
+    for id in [0, 1, 2, 3, 500, 1002, 1003, 5000] {
+        assert_eq!(
+            MySpace::kind_of(id),
+            MySpace::try_from(id).ok().map(|token| token.kind())
+        );
+    }
+}
+
+#[test]
+fn test_segments() {
+    assert_eq!(MySpace::SEGMENTS.len(), 2);
+    assert_eq!(MySpace::DYNAMIC, Some(MySpaceKind::Vocab));
+    assert_eq!(ClosedSpace::DYNAMIC, None);
+
+    let control = MySpace::segment(MySpaceKind::Control).unwrap();
+    assert_eq!(control.name, "Control");
+    assert_eq!(control.range(), 0..3);
+
+    let text = MySpace::segment(MySpaceKind::Text).unwrap();
+    assert_eq!(text.offset, <MySpace as Position<TextTokens>>::OFFSET);
+    assert_eq!(text.end(), MySpace::RESERVED);
+    assert!(text.contains(3));
+    assert!(!text.contains(1003));
+
+    assert_eq!(MySpace::segment(MySpaceKind::Vocab), None);
+
+    let empty = ClosedSpace::segment(ClosedSpaceKind::Empty).unwrap();
+    assert_eq!(empty.range(), 0..0);
+
+    // Layout fingerprints tell differently shaped spaces apart
+    assert_ne!(MySpace::FINGERPRINT, ClosedSpace::FINGERPRINT);
+    assert!(MySpace::verify_fingerprint(MySpace::FINGERPRINT).is_ok());
+}
+
+#[test]
+fn test_kinds_iter() {
+    let ids: Vec<u32> = vec![0, 1, 50, 100, 1010, 2000, 2];
+
+    let kinds: Vec<Option<MySpaceKind>> = ids.clone().into_iter().kinds::<MySpace>().collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Some(MySpaceKind::Control),
+            Some(MySpaceKind::Control),
+            Some(MySpaceKind::Text),
+            Some(MySpaceKind::Text),
+            Some(MySpaceKind::Vocab),
+            Some(MySpaceKind::Vocab),
+            Some(MySpaceKind::Control),
+        ]
+    );
+
+    // Bucket and count tokens per segment
+    let mut counts: HashMap<MySpaceKind, usize> = HashMap::new();
+    for kind in ids.into_iter().kinds::<MySpace>().flatten() {
+        *counts.entry(kind).or_default() += 1;
+    }
+    assert_eq!(counts[&MySpaceKind::Control], 3);
+    assert_eq!(counts[&MySpaceKind::Text], 2);
+    assert_eq!(counts[&MySpaceKind::Vocab], 2);
+
+    let closed: Vec<Option<ClosedSpaceKind>> =
+        vec![0u32, 3].into_iter().kinds::<ClosedSpace>().collect();
+    assert_eq!(closed, vec![Some(ClosedSpaceKind::Control), None]);
+}
@@ -1,4 +1,4 @@
-use tokau::{Name, Position, Space, Token, TokenSpace, range};
+use tokau::{Name, Position, Space, TokauError, Token, TokenSpace, range};
 
 #[derive(Name, Debug, PartialEq, Clone, Copy)]
 #[repr(u32)]
@@ -20,6 +20,12 @@ enum MySpace {
     Vocab(u32),
 }
 
+#[derive(Space, Debug, PartialEq, Clone, Copy)]
+enum StaticSpace {
+    Control(ControlToken),
+    Text(TextTokens),
+}
+
 #[test]
 fn test_space_macro() {
     // Test that Position implementations were generated correctly
@@ -228,3 +234,54 @@ fn test_space_value_with_boundaries() {
     assert!(!MySpace::is_reserved(1003)); // Dynamic
     assert!(!MySpace::is_reserved(5000)); // Dynamic
 }
+
+#[test]
+fn test_checked_dynamic_arithmetic() {
+    // MySpace has RESERVED = 1003
+    assert_eq!(MySpace::checked_after_reserved(5), Some(1008));
+    assert_eq!(
+        MySpace::checked_after_reserved(u32::MAX - 1003),
+        Some(u32::MAX)
+    );
+    assert_eq!(MySpace::checked_after_reserved(u32::MAX - 1002), None);
+
+    assert_eq!(MySpace::Vocab(5).try_value(), Ok(1008));
+    assert_eq!(MySpace::Vocab(u32::MAX - 1003).try_value(), Ok(u32::MAX));
+    assert_eq!(
+        MySpace::Vocab(u32::MAX).try_value(),
+        Err(TokauError::Overflow {
+            value: u32::MAX,
+            offset: 1003,
+        })
+    );
+    assert_eq!(MySpace::Text(TextTokens(999)).try_value(), Ok(1002));
+    assert_eq!(MySpace::Control(ControlToken::Pause).try_value(), Ok(2));
+}
+
+#[test]
+fn test_try_value_out_of_range_token() {
+    assert_eq!(StaticSpace::Text(TextTokens(999)).try_value(), Ok(1002));
+
+    // Range tokens built by hand can hold any u32
+    assert_eq!(
+        StaticSpace::Text(TextTokens(1000)).try_value(),
+        Err(TokauError::OutOfRange {
+            value: 1000,
+            max: 1000,
+        })
+    );
+    assert_eq!(
+        StaticSpace::Text(TextTokens(u32::MAX)).try_value(),
+        Err(TokauError::Overflow {
+            value: u32::MAX,
+            offset: 3,
+        })
+    );
+    assert_eq!(
+        MySpace::Text(TextTokens(u32::MAX)).try_value(),
+        Err(TokauError::Overflow {
+            value: u32::MAX,
+            offset: 3,
+        })
+    );
+}
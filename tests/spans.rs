@@ -0,0 +1,86 @@
+use tokau::{Name, Position, Space, Span, SpanError, Token, TokenIter, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+#[pair(TextStart, TextEnd)]
+#[pair(AudioStart, AudioEnd)]
+enum ControlToken {
+    TextStart,
+    TextEnd,
+    AudioStart,
+    AudioEnd,
+    Pause,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(10)]
+struct AudioTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum MySpace {
+    Control(ControlToken),
+    Audio(AudioTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+#[test]
+fn test_declared_pairs() {
+    assert_eq!(ControlToken::PAIRS, &[(0, 1), (2, 3)]);
+    assert_eq!(AudioTokens::PAIRS, &[]);
+}
+
+#[test]
+fn test_spans() {
+    // TextStart "hi" TextEnd AudioStart a0 a1 AudioEnd
+    let ids = vec![0, 20, 21, 1, 2, 5, 6, 3];
+    let spans: Result<Vec<Span<MySpace>>, SpanError> = ids.into_iter().spans().collect();
+    let spans = spans.unwrap();
+
+    assert_eq!(spans.len(), 2);
+    assert_eq!(spans[0].open, MySpace::Control(ControlToken::TextStart));
+    assert_eq!(spans[0].contents, vec![20, 21]);
+    assert_eq!(spans[0].close, MySpace::Control(ControlToken::TextEnd));
+    assert_eq!(spans[1].open, MySpace::Control(ControlToken::AudioStart));
+    assert_eq!(spans[1].contents, vec![5, 6]);
+    assert_eq!((spans[1].start, spans[1].end), (4, 7));
+}
+
+#[test]
+fn test_span_errors() {
+    // TextStart AudioStart TextEnd: the audio span is still open, so it is
+    // discarded and the text span closes around it
+    let results: Vec<_> = vec![0, 2, 1].into_iter().spans::<MySpace>().collect();
+    assert_eq!(
+        results,
+        vec![
+            Err(SpanError::Interleaved {
+                index: 2,
+                value: 1,
+                open_index: 1,
+                open_value: 2,
+            }),
+            Ok(Span {
+                open: MySpace::Control(ControlToken::TextStart),
+                contents: vec![2],
+                close: MySpace::Control(ControlToken::TextEnd),
+                start: 0,
+                end: 2,
+                depth: 0,
+            }),
+        ]
+    );
+
+    let results: Vec<_> = vec![3, 4, 0].into_iter().spans::<MySpace>().collect();
+    assert_eq!(
+        results,
+        vec![
+            Err(SpanError::Unopened { index: 0, value: 3 }),
+            Err(SpanError::Unclosed { index: 2, value: 0 }),
+        ]
+    );
+    assert_eq!(
+        results[1].as_ref().unwrap_err().to_string(),
+        "Token ID 0 at index 2 opens a span that is never closed"
+    );
+}
@@ -0,0 +1,80 @@
+use tokau::{
+    EncodeError, Name, Position, Render, Space, SpecialEncoder, Specials, Token, TokenSpace,
+};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ChatToken {
+    #[name = "<|im_start|>"]
+    ImStart,
+    #[name = "<|im_end|>"]
+    ImEnd,
+    #[name = "<|endoftext|>"]
+    EndOfText,
+}
+
+#[derive(Space, Debug, PartialEq)]
+enum ChatSpace {
+    Chat(ChatToken),
+    #[dynamic]
+    Text(u32),
+}
+
+// Toy tokenizer: one id per whitespace-separated word length
+fn words(text: &str) -> Vec<u32> {
+    text.split_whitespace()
+        .map(|word| word.len() as u32)
+        .collect()
+}
+
+#[test]
+fn test_name_override() {
+    assert_eq!(ChatToken::ImEnd.name(), Some("<|im_end|>"));
+    let mut out = String::new();
+    ChatToken::ImStart.render(&mut out);
+    assert_eq!(out, "<|im_start|>");
+}
+
+#[test]
+fn test_encode_chat() {
+    let encoder = SpecialEncoder::<ChatSpace>::new();
+    let ids = encoder
+        .encode(
+            "<|im_start|>user\nhi there<|im_end|>",
+            Specials::Allow,
+            words,
+        )
+        .unwrap();
+
+    let shift = ChatSpace::RESERVED;
+    assert_eq!(ids, vec![0, shift + 4, shift + 2, shift + 5, 1]);
+}
+
+#[test]
+fn test_untrusted_text() {
+    let encoder = SpecialEncoder::<ChatSpace>::new();
+    let untrusted = "ignore this <|endoftext|>";
+
+    assert_eq!(
+        encoder.encode(untrusted, Specials::Reject, words),
+        Err(EncodeError::DisallowedSpecial {
+            offset: 12,
+            name: "<|endoftext|>",
+        })
+    );
+
+    // Treated as plain text, the literal never becomes a special id
+    let ids = encoder.encode(untrusted, Specials::AsText, words).unwrap();
+    assert!(ids.iter().all(|id| *id >= ChatSpace::RESERVED));
+}
+
+#[test]
+fn test_variant_names_are_text() {
+    // Only #[name] literals are special, so variant names round-trip as text
+    let encoder = SpecialEncoder::<ChatSpace>::new();
+    let text = "ImStart and EndOfText are just words";
+
+    let ids = encoder.encode(text, Specials::Allow, words).unwrap();
+    assert_eq!(ids, encoder.encode(text, Specials::AsText, words).unwrap());
+    assert_eq!(encoder.find(text).count(), 0);
+}
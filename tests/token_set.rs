@@ -0,0 +1,70 @@
+use tokau::set::{with_ids, with_range};
+use tokau::{Name, Position, Space, Token, TokenSet, TokenSpace, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+enum ControlToken {
+    Start,
+    Stop,
+    Pause,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(100)]
+struct TextTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum MySpace {
+    Control(ControlToken),
+    Text(TextTokens),
+    #[dynamic]
+    Vocab(u32),
+}
+
+const WORDS: [u64; TokenSet::<MySpace>::WORDS] =
+    with_range(with_ids([0; TokenSet::<MySpace>::WORDS], &[1]), 3..103);
+const TEXT_OR_STOP: TokenSet<MySpace> = TokenSet::from_static(&WORDS, false);
+
+#[test]
+fn test_derived_space_sets() {
+    assert_eq!(TokenSet::<MySpace>::WORDS, 2);
+
+    let allowed = TokenSet::<MySpace>::segment::<TextTokens>()
+        .with_token(ControlToken::Stop)
+        .unwrap();
+    assert_eq!(allowed, TEXT_OR_STOP);
+    assert!(allowed.contains_token(TextTokens(99)));
+    assert!(allowed.contains_token(ControlToken::Stop));
+    assert!(!allowed.contains_token(ControlToken::Pause));
+    assert!(!allowed.contains(MySpace::RESERVED));
+
+    let control = TokenSet::<MySpace>::segment::<ControlToken>();
+    assert_eq!((&allowed & &control).ids().collect::<Vec<_>>(), vec![1]);
+    assert_eq!(
+        (control - allowed.clone()).ids().collect::<Vec<_>>(),
+        vec![0, 2]
+    );
+
+    // Anything but text still allows dynamic vocabulary
+    let not_text = !TokenSet::<MySpace>::segment::<TextTokens>();
+    assert!(not_text.contains(0));
+    assert!(not_text.contains(5000));
+    assert!(!not_text.contains(50));
+    assert_eq!(not_text.reserved_len(), 3);
+}
+
+#[test]
+fn test_ids_decode_to_members() {
+    let ids: Vec<MySpace> = TEXT_OR_STOP
+        .ids()
+        .take(2)
+        .map(|id| MySpace::try_from(id).unwrap())
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            MySpace::Control(ControlToken::Stop),
+            MySpace::Text(TextTokens(0))
+        ]
+    );
+}
@@ -0,0 +1,74 @@
+use tokau::{Name, Position, Space, SpanPolicy, Token, TokenIter, TokenSpace, Truncator, range};
+
+#[derive(Name, Debug, PartialEq, Clone, Copy)]
+#[repr(u32)]
+#[pair(AudioStart, AudioEnd)]
+enum Control {
+    AudioStart,
+    AudioEnd,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[range(16)]
+struct AudioTokens(u32);
+
+#[derive(Space, Debug, PartialEq)]
+enum Speech {
+    Control(Control),
+    Audio(AudioTokens),
+    #[dynamic]
+    Text(u32),
+}
+
+fn sequence() -> Vec<u32> {
+    let text = |n: u32| Speech::after_reserved(n);
+    let audio = |n: u32| Speech::position_of(AudioTokens(n));
+    let open = Speech::position_of(Control::AudioStart);
+    let close = Speech::position_of(Control::AudioEnd);
+    vec![
+        text(0),
+        text(1),
+        open,
+        audio(0),
+        audio(1),
+        audio(2),
+        audio(3),
+        close,
+        text(2),
+        open,
+        audio(4),
+        close,
+    ]
+}
+
+#[test]
+fn test_chunks_stay_well_formed() {
+    let ids = sequence();
+    for policy in [SpanPolicy::Drop, SpanPolicy::Close] {
+        for max_len in 3..=12 {
+            let chunks = Truncator::<Speech>::new(max_len, policy).chunks(&ids, 1);
+            assert_eq!(chunks.last().unwrap().range.end, ids.len());
+            for chunk in chunks {
+                assert!(chunk.ids.len() <= max_len, "{policy:?} {max_len}");
+                assert!(
+                    chunk
+                        .ids
+                        .into_iter()
+                        .spans::<Speech>()
+                        .all(|span| span.is_ok()),
+                    "{policy:?} {max_len}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_truncate_reports_cut() {
+    let ids = sequence();
+    let chunk = Truncator::<Speech>::new(5, SpanPolicy::Close).truncate(&ids);
+    assert_eq!(chunk.range, 0..4);
+    assert_eq!(chunk.split, vec![2]);
+    assert_eq!(chunk.closed, 1);
+    assert_eq!(*chunk.ids.last().unwrap(), 1);
+}
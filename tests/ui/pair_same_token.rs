@@ -0,0 +1,11 @@
+use tokau::Name;
+
+#[derive(Name, Clone, Copy)]
+#[repr(u32)]
+#[pair(Quote, Quote)]
+enum MarkerToken {
+    Quote,
+    Pause,
+}
+
+fn main() {}
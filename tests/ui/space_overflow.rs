@@ -0,0 +1,15 @@
+use tokau::{Position, Space, range};
+
+#[range(4000000000)]
+struct Audio(u32);
+
+#[range(4000000000)]
+struct Video(u32);
+
+#[derive(Space)]
+enum Media {
+    Audio(Audio),
+    Video(Video),
+}
+
+fn main() {}
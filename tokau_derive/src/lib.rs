@@ -1,11 +1,36 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{Data, DeriveInput, Fields, LitInt, Type, parse_macro_input};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    Data, DeriveInput, Fields, Ident, LitInt, LitStr, Path, Type, Visibility, parse_macro_input,
+};
+
+// Arguments of #[range(1000)] or #[range(1000, format = "<audio_{}>")]
+struct RangeArgs {
+    count: LitInt,
+    format: Option<LitStr>,
+}
+
+impl Parse for RangeArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let count = input.parse()?;
+        let mut format = None;
+        if input.parse::<Option<syn::Token![,]>>()?.is_some() && !input.is_empty() {
+            let key: Ident = input.parse()?;
+            if key != "format" {
+                return Err(syn::Error::new_spanned(key, "expected `format = \"...\"`"));
+            }
+            input.parse::<syn::Token![=]>()?;
+            format = Some(input.parse()?);
+        }
+        Ok(RangeArgs { count, format })
+    }
+}
 
 // Attribute macro for cleaner syntax: #[range(1000)]
 #[proc_macro_attribute]
 pub fn range(args: TokenStream, input: TokenStream) -> TokenStream {
-    let count = parse_macro_input!(args as LitInt);
+    let RangeArgs { count, format } = parse_macro_input!(args as RangeArgs);
     let input = parse_macro_input!(input as DeriveInput);
 
     let name = &input.ident;
@@ -38,10 +63,19 @@ pub fn range(args: TokenStream, input: TokenStream) -> TokenStream {
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
+    // Render as e.g. `AudioTokens(42)` unless a format pattern is given
+    let format = format.unwrap_or_else(|| LitStr::new(&format!("{}({{}})", name), name.span()));
+
     let expanded = quote! {
         #(#attrs)*
         #vis struct #name #generics #fields;
 
+        impl #impl_generics ::tokau::Render for #name #ty_generics #where_clause {
+            fn render(&self, out: &mut String) {
+                let _ = ::std::fmt::Write::write_fmt(out, format_args!(#format, self.0));
+            }
+        }
+
         impl #impl_generics ::tokau::Token for #name #ty_generics #where_clause {
             const COUNT: u32 = #count;
 
@@ -74,6 +108,7 @@ pub fn derive_space(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let name = &input.ident;
+    let vis = &input.vis;
 
     // Must be an enum
     let variants = match &input.data {
@@ -85,8 +120,8 @@ pub fn derive_space(input: TokenStream) -> TokenStream {
         }
     };
 
-    // Collect token types and check for dynamic variant
-    let mut token_types = Vec::new();
+    // Collect (variant, token type) segments and check for dynamic variant
+    let mut segments = Vec::new();
     let mut dynamic_field = None;
 
     for variant in variants {
@@ -110,10 +145,10 @@ pub fn derive_space(input: TokenStream) -> TokenStream {
             // Extract the token type from the variant
             match &variant.fields {
                 Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
-                    if let Some(field) = fields.unnamed.first() {
-                        if let Type::Path(type_path) = &field.ty {
-                            token_types.push(type_path.path.clone());
-                        }
+                    if let Some(field) = fields.unnamed.first()
+                        && let Type::Path(type_path) = &field.ty
+                    {
+                        segments.push((&variant.ident, type_path.path.clone()));
                     }
                 }
                 _ => {
@@ -128,6 +163,8 @@ pub fn derive_space(input: TokenStream) -> TokenStream {
         }
     }
 
+    let token_types: Vec<_> = segments.iter().map(|(_, token_type)| token_type).collect();
+
     // Generate Position implementations
     let mut position_impls = Vec::new();
     let mut offset_expr = quote! { 0 };
@@ -156,6 +193,15 @@ pub fn derive_space(input: TokenStream) -> TokenStream {
         quote! { #(#counts)+* }
     };
 
+    // Reject layouts whose RESERVED would overflow u32, summing in u64
+    let overflow_message = format!("token space {} overflows u32", name);
+    let layout_assertion = quote! {
+        const _: () = assert!(
+            0u64 #(+ <#token_types as ::tokau::Token>::COUNT as u64)* <= u32::MAX as u64,
+            #overflow_message
+        );
+    };
+
     // Generate decode method implementation - use try_as<T>() for simplicity and correctness
     // TODO: Optimize this to generate efficient jump-table with match statement and literal range bounds
     // Current approach uses multiple try_as<T>() calls which do redundant offset calculations.
@@ -165,37 +211,20 @@ pub fn derive_space(input: TokenStream) -> TokenStream {
     // - Use const evaluation tricks or const blocks to compute bounds at compile time
     // - Generate numeric literals by evaluating token counts during macro expansion
     // - Hybrid approach with both current fallback and optimized match version
-    let mut decode_arms = Vec::new();
-
-    // Add arms for each token type using is<T>() calls
-    for (variant, token_type) in variants.iter().zip(&token_types) {
-        let is_dynamic = variant
-            .attrs
-            .iter()
-            .any(|attr| attr.path().is_ident("dynamic"));
-        if !is_dynamic {
-            let variant_name = &variant.ident;
-            decode_arms.push(quote! {
-                if let Some(token) = <#name as ::tokau::TokenSpace>::try_as::<#token_type>(id) {
-                    return Ok(#name::#variant_name(token));
-                }
-            });
-        }
-    }
+    let mut decode_conditions = Vec::new();
+    let mut decoded = Vec::new();
 
-    // Add dynamic variant if present
-    if let Some(dynamic_variant) = &dynamic_field {
-        decode_arms.push(quote! {
-            if let Some(offset) = <#name as ::tokau::TokenSpace>::remainder(id) {
-                return Ok(#name::#dynamic_variant(offset));
-            }
+    // Add arms for each token type using try_as<T>() calls, binding `token`
+    for (variant_name, token_type) in &segments {
+        decode_conditions.push(quote! {
+            let Some(token) = <#name as ::tokau::TokenSpace>::try_as::<#token_type>(id)
         });
+        decoded.push(quote! { #name::#variant_name(token) });
     }
 
     // Generate value() method implementation
     let mut value_arms = Vec::new();
-    for (variant, _token_type) in variants.iter().zip(&token_types) {
-        let variant_name = &variant.ident;
+    for (variant_name, _token_type) in &segments {
         value_arms.push(quote! {
             #name::#variant_name(token) => <#name as ::tokau::TokenSpace>::position_of(token)
         });
@@ -204,10 +233,80 @@ pub fn derive_space(input: TokenStream) -> TokenStream {
     // Add dynamic variant value arm if present
     if let Some(dynamic_variant) = &dynamic_field {
         value_arms.push(quote! {
-            #name::#dynamic_variant(offset) => Self::RESERVED + offset
+            #name::#dynamic_variant(offset) => <#name as ::tokau::TokenSpace>::RESERVED + offset
+        });
+    }
+
+    // Check segment tokens against their COUNT, hand-built range tokens can
+    // hold any u32, and dynamic offsets against the end of u32
+    let mut try_value_arms: Vec<_> = segments
+        .iter()
+        .map(|(variant_name, token_type)| {
+            quote! {
+                #name::#variant_name(token) => <Self as ::tokau::Position<#token_type>>::try_at(token)
+            }
+        })
+        .collect();
+    if let Some(dynamic_variant) = &dynamic_field {
+        try_value_arms.push(quote! {
+            #name::#dynamic_variant(offset) => {
+                <Self as ::tokau::TokenSpace>::checked_after_reserved(offset).ok_or(
+                    ::tokau::TokauError::Overflow {
+                        value: offset,
+                        offset: <Self as ::tokau::TokenSpace>::RESERVED,
+                    },
+                )
+            }
         });
     }
 
+    // Spaces with a dynamic tail decode every id at or above RESERVED, reserved
+    // ids still fail when a hand-written token's TryFrom leaves them unclaimed
+    let decode_impl = match &dynamic_field {
+        Some(dynamic_variant) => quote! {
+            impl TryFrom<u32> for #name {
+                type Error = ::tokau::TokauError;
+
+                fn try_from(id: u32) -> Result<Self, Self::Error> {
+                    if id >= <Self as ::tokau::TokenSpace>::RESERVED {
+                        return Ok(#name::#dynamic_variant(id - <Self as ::tokau::TokenSpace>::RESERVED));
+                    }
+                    #(if #decode_conditions {
+                        return Ok(#decoded);
+                    })*
+                    Err(::tokau::TokauError::InvalidId { value: id.into() })
+                }
+            }
+
+            impl ::tokau::OpenSpace for #name {
+                fn decode_total(id: u32) -> Self {
+                    Self::try_from(id).unwrap_or_else(|error| panic!("{}", error))
+                }
+            }
+        },
+        None => quote! {
+            impl TryFrom<u32> for #name {
+                type Error = ::tokau::TokauError;
+
+                fn try_from(id: u32) -> Result<Self, Self::Error> {
+                    #(if #decode_conditions {
+                        return Ok(#decoded);
+                    })*
+                    Err(::tokau::TokauError::OutOfRange {
+                        value: id,
+                        max: <Self as ::tokau::TokenSpace>::RESERVED
+                    })
+                }
+            }
+
+            impl ::tokau::ClosedSpace for #name {}
+        },
+    };
+
+    let conversion_impl = derive_conversions(name, &segments, &value_arms);
+    let kind_impl = derive_kind(name, vis, &segments, dynamic_field.as_ref());
+    let render_impl = derive_render(name, &segments, dynamic_field.as_ref());
+
     let expanded = quote! {
         #(#position_impls)*
 
@@ -215,29 +314,229 @@ pub fn derive_space(input: TokenStream) -> TokenStream {
             const RESERVED: u32 = #reserved_expr;
 
             fn value(self) -> u32 {
+                u32::from(self)
+            }
+
+            fn try_value(self) -> Result<u32, ::tokau::TokauError> {
                 match self {
+                    #(#try_value_arms,)*
+                }
+            }
+        }
+
+        #layout_assertion
+
+        #decode_impl
+
+        #conversion_impl
+
+        #kind_impl
+
+        #render_impl
+    };
+
+    TokenStream::from(expanded)
+}
+
+// Generate conversions between the space, its segment token types and raw ids
+fn derive_conversions(
+    name: &Ident,
+    segments: &[(&Ident, Path)],
+    value_arms: &[proc_macro2::TokenStream],
+) -> proc_macro2::TokenStream {
+    let segment_impls = segments.iter().map(|(variant_name, token_type)| {
+        quote! {
+            impl From<#token_type> for #name {
+                fn from(token: #token_type) -> Self {
+                    #name::#variant_name(token)
+                }
+            }
+
+            impl TryFrom<#name> for #token_type {
+                type Error = #name;
+
+                #[allow(unreachable_patterns)]
+                fn try_from(space: #name) -> Result<Self, Self::Error> {
+                    match space {
+                        #name::#variant_name(token) => Ok(token),
+                        other => Err(other),
+                    }
+                }
+            }
+        }
+    });
+
+    // Segment types are distinct, so the variant holding T is the one at T's offset
+    let get_arms = segments.iter().map(|(variant_name, token_type)| {
+        quote! {
+            #name::#variant_name(token)
+                if <Self as ::tokau::Position<T>>::OFFSET
+                    == <Self as ::tokau::Position<#token_type>>::OFFSET =>
+            {
+                T::try_from(::tokau::Token::value(token)).ok()
+            }
+        }
+    });
+
+    quote! {
+        #(#segment_impls)*
+
+        // `TokenSpace::value` requires Copy, so it defers to this instead
+        impl From<#name> for u32 {
+            fn from(space: #name) -> u32 {
+                match space {
                     #(#value_arms,)*
                 }
             }
         }
 
-        impl TryFrom<u32> for #name {
-            type Error = ::tokau::TokauError;
+        impl #name {
+            /// Get the token held by this value as type `T`, if it belongs to `T`'s segment
+            pub fn get<T>(&self) -> Option<T>
+            where
+                Self: ::tokau::Position<T>,
+                T: ::tokau::Token + TryFrom<u32, Error = ::tokau::TokauError>,
+            {
+                match self {
+                    #(#get_arms)*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+// Render the space by delegating to each token type. The bounds are written as
+// higher-ranked so a space whose tokens don't all implement Render still
+// compiles, it just doesn't implement Render itself.
+fn derive_render(
+    name: &Ident,
+    segments: &[(&Ident, Path)],
+    dynamic_field: Option<&Ident>,
+) -> proc_macro2::TokenStream {
+    let bounds = segments.iter().map(|(_, token_type)| {
+        quote! { for<'__r> #token_type: ::tokau::Render }
+    });
+
+    let arms = segments.iter().map(|(variant_name, _)| {
+        quote! { #name::#variant_name(token) => ::tokau::Render::render(token, out) }
+    });
+
+    // Dynamic values need a vocabulary, see `tokau::render::render`
+    let dynamic_arm = dynamic_field.map(|dynamic_variant| {
+        quote! { #name::#dynamic_variant(_) => {} }
+    });
+
+    quote! {
+        impl ::tokau::Render for #name
+        where
+            #(#bounds,)*
+        {
+            fn render(&self, out: &mut String) {
+                match self {
+                    #(#arms,)*
+                    #dynamic_arm
+                }
+            }
+        }
+    }
+}
+
+// Generate the fieldless `<Space>Kind` enum and the `Segmented` implementation
+fn derive_kind(
+    name: &Ident,
+    vis: &Visibility,
+    segments: &[(&Ident, Path)],
+    dynamic_field: Option<&Ident>,
+) -> proc_macro2::TokenStream {
+    let kind_name = format_ident!("{}Kind", name);
+    let kind_doc = format!("Segment kinds of [`{}`], one per variant", name);
+
+    let kind_variants: Vec<_> = segments
+        .iter()
+        .map(|(variant_name, _)| *variant_name)
+        .chain(dynamic_field)
+        .collect();
+
+    let segment_entries = segments.iter().map(|(variant_name, token_type)| {
+        let segment_name = variant_name.to_string();
+        quote! {
+            ::tokau::Segment {
+                kind: #kind_name::#variant_name,
+                name: #segment_name,
+                offset: <#name as ::tokau::Position<#token_type>>::OFFSET,
+                count: <#token_type as ::tokau::Token>::COUNT,
+                tokens: <#token_type as ::tokau::Token>::NAMES,
+                variants: <#token_type as ::tokau::Token>::VARIANTS,
+                pairs: <#token_type as ::tokau::Token>::PAIRS,
+            }
+        }
+    });
 
-            fn try_from(id: u32) -> Result<Self, Self::Error> {
-                #(#decode_arms)*
-                Err(::tokau::TokauError::OutOfRange {
-                    value: id,
-                    max: Self::RESERVED
-                })
+    // Segments are contiguous from 0, so the first segment ending past `id` owns it
+    let kind_of_arms = segments.iter().map(|(variant_name, token_type)| {
+        quote! {
+            if id < <#name as ::tokau::Position<#token_type>>::OFFSET
+                + <#token_type as ::tokau::Token>::COUNT
+            {
+                return Some(#kind_name::#variant_name);
             }
         }
+    });
+
+    let dynamic_kind = match dynamic_field {
+        Some(dynamic_variant) => quote! { Some(#kind_name::#dynamic_variant) },
+        None => quote! { None },
     };
 
-    TokenStream::from(expanded)
+    // The dynamic tail has no segment, so its name is matched separately
+    let kind_named = dynamic_field.map(|dynamic_variant| {
+        let dynamic_name = dynamic_variant.to_string();
+        quote! {
+            fn kind_named(name: &str) -> Option<#kind_name> {
+                match name {
+                    #dynamic_name => Self::DYNAMIC,
+                    _ => Self::segment_named(name).map(|segment| segment.kind),
+                }
+            }
+        }
+    });
+
+    let kind_arms = kind_variants.iter().map(|variant_name| {
+        quote! { #name::#variant_name(_) => #kind_name::#variant_name }
+    });
+
+    quote! {
+        #[doc = #kind_doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #vis enum #kind_name {
+            #(#kind_variants,)*
+        }
+
+        impl ::tokau::Segmented for #name {
+            type Kind = #kind_name;
+
+            const SEGMENTS: &'static [::tokau::Segment<#kind_name>] = &[#(#segment_entries,)*];
+
+            const DYNAMIC: Option<#kind_name> = #dynamic_kind;
+
+            fn kind_of(id: u32) -> Option<#kind_name> {
+                #(#kind_of_arms)*
+                Self::DYNAMIC
+            }
+
+            fn kind(&self) -> #kind_name {
+                match self {
+                    #(#kind_arms,)*
+                }
+            }
+
+            #kind_named
+        }
+    }
 }
 
-#[proc_macro_derive(Name)]
+#[proc_macro_derive(Name, attributes(pair, name))]
 pub fn derive_name(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -253,6 +552,77 @@ pub fn derive_name(input: TokenStream) -> TokenStream {
     };
 
     let count = variants.len() as u32;
+    let variant_names = variants.iter().map(|variant| variant.ident.to_string());
+    // Names default to the variant name, #[name = "<|im_start|>"] overrides it
+    let mut names = Vec::new();
+    for variant in variants {
+        let mut token_name = variant.ident.to_string();
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("name"))
+        {
+            let value = match &attr.meta {
+                syn::Meta::NameValue(meta) => &meta.value,
+                _ => {
+                    return syn::Error::new_spanned(attr, "expected #[name = \"...\"]")
+                        .to_compile_error()
+                        .into();
+                }
+            };
+            match value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(lit),
+                    ..
+                }) => token_name = lit.value(),
+                _ => {
+                    return syn::Error::new_spanned(value, "expected a string literal")
+                        .to_compile_error()
+                        .into();
+                }
+            }
+        }
+        names.push(token_name);
+    }
+
+    // #[pair(Open, Close)] declares a start/end pair by variant name
+    let mut pairs = Vec::new();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("pair"))
+    {
+        let idents = match attr
+            .parse_args_with(syn::punctuated::Punctuated::<Ident, syn::Token![,]>::parse_terminated)
+        {
+            Ok(idents) => idents,
+            Err(err) => return err.to_compile_error().into(),
+        };
+        if idents.len() != 2 {
+            return syn::Error::new_spanned(attr, "expected #[pair(Open, Close)]")
+                .to_compile_error()
+                .into();
+        }
+        let mut values = Vec::new();
+        for ident in &idents {
+            match variants.iter().position(|variant| &variant.ident == ident) {
+                Some(index) => values.push(index as u32),
+                None => {
+                    return syn::Error::new_spanned(ident, format!("no variant named `{}`", ident))
+                        .to_compile_error()
+                        .into();
+                }
+            }
+        }
+        let (open, close) = (values[0], values[1]);
+        // A token that both opens and closes a span would never close it
+        if open == close {
+            return syn::Error::new_spanned(attr, "a pair needs different open and close tokens")
+                .to_compile_error()
+                .into();
+        }
+        pairs.push(quote! { (#open, #close) });
+    }
 
     let try_from_arms = variants.iter().enumerate().map(|(i, variant)| {
         let variant_name = &variant.ident;
@@ -267,11 +637,23 @@ pub fn derive_name(input: TokenStream) -> TokenStream {
         impl ::tokau::Token for #name {
             const COUNT: u32 = #count;
 
+            const NAMES: &'static [&'static str] = &[#(#names),*];
+
+            const VARIANTS: &'static [&'static str] = &[#(#variant_names),*];
+
+            const PAIRS: &'static [(u32, u32)] = &[#(#pairs),*];
+
             fn value(&self) -> u32 {
                 *self as u32
             }
         }
 
+        impl ::tokau::Render for #name {
+            fn render(&self, out: &mut String) {
+                out.push_str(<Self as ::tokau::Token>::NAMES[<Self as ::tokau::Token>::value(self) as usize]);
+            }
+        }
+
         impl TryFrom<u32> for #name {
             type Error = ::tokau::TokauError;
 